@@ -0,0 +1,104 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::turing_machine::core::MultiCommand;
+
+/// Commands that have fired in any of the recorded runs of the same program.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    commands: Vec<MultiCommand>,
+    fired: Vec<bool>,
+}
+
+impl Coverage {
+    /// Records the fires of a run, starting anew if the run had other commands.
+    pub fn record(&mut self, commands: &[MultiCommand], fires: &[usize]) {
+        if self.commands != commands {
+            self.commands = commands.to_owned();
+            self.fired = vec![false; commands.len()];
+        }
+        for (fired, &n) in self.fired.iter_mut().zip(fires) {
+            *fired |= n > 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns whether the command has fired, or `None` if nothing is recorded for these commands.
+    pub fn is_fired(&self, commands: &[MultiCommand], index: usize) -> Option<bool> {
+        if self.commands != commands {
+            return None;
+        }
+        self.fired.get(index).copied()
+    }
+
+    pub fn percentage(&self, commands: &[MultiCommand]) -> Option<f64> {
+        if self.commands != commands || commands.is_empty() {
+            return None;
+        }
+        let fired = self.fired.iter().filter(|&&x| x).count();
+        Some(fired as f64 * 100.0 / commands.len() as f64)
+    }
+}
+
+pub fn add_fires(total: &mut Vec<usize>, fires: &[usize]) {
+    if total.len() < fires.len() {
+        total.resize(fires.len(), 0);
+    }
+    for (total, n) in total.iter_mut().zip(fires) {
+        *total += n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_cmd;
+
+    #[test]
+    fn test_1() {
+        let commands: Vec<MultiCommand> = vec![
+            tm_cmd!("q0", 'a', "q0", 'a', 'R').into(),
+            tm_cmd!("q0", 'b', "q1", 'b', 'N').into(),
+            tm_cmd!("q1", 'b', "q1", 'a', 'N').into(),
+            tm_cmd!("q1", 'a', "q1", 'b', 'N').into(),
+        ];
+        let mut coverage = Coverage::default();
+        assert_eq!(coverage.percentage(&commands), None);
+        coverage.record(&commands, &[3]);
+        assert_eq!(coverage.percentage(&commands), Some(25.0));
+        coverage.record(&commands, &[0, 1, 0]);
+        assert_eq!(coverage.percentage(&commands), Some(50.0));
+        assert_eq!(coverage.is_fired(&commands, 1), Some(true));
+        assert_eq!(coverage.is_fired(&commands, 2), Some(false));
+        assert_eq!(coverage.is_fired(&commands[..3], 1), None);
+    }
+
+    #[test]
+    fn test_2() {
+        let mut commands: Vec<MultiCommand> = vec![
+            tm_cmd!("q0", 'a', "q0", 'a', 'R').into(),
+            tm_cmd!("q0", 'b', "q1", 'b', 'N').into(),
+        ];
+        let mut coverage = Coverage::default();
+        coverage.record(&commands, &[1, 1]);
+        assert_eq!(coverage.percentage(&commands), Some(100.0));
+        commands.pop();
+        assert_eq!(coverage.percentage(&commands), None);
+        coverage.record(&commands, &[0]);
+        assert_eq!(coverage.percentage(&commands), Some(0.0));
+        coverage.clear();
+        assert_eq!(coverage.is_fired(&commands, 0), None);
+    }
+
+    #[test]
+    fn test_add_fires() {
+        let mut total = vec![1];
+        add_fires(&mut total, &[0, 2]);
+        add_fires(&mut total, &[3]);
+        assert_eq!(total, [4, 2]);
+    }
+}
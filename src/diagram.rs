@@ -0,0 +1,372 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use eframe::egui::{
+    self,
+    epaint::{CubicBezierShape, QuadraticBezierShape},
+    pos2, vec2, Align2, Color32, FontId, Pos2, Sense, Shape, Stroke, Vec2,
+};
+
+use crate::turing_machine::core::{bfs_states, MultiCommand};
+
+const NODE_RADIUS: f32 = 20.0;
+const COLUMN_WIDTH: f32 = 170.0;
+const ROW_HEIGHT: f32 = 100.0;
+
+pub struct Node {
+    pub state: String,
+    pub column: usize,
+    pub pos: Pos2,
+    pub is_start: bool,
+    pub is_halting: bool,
+}
+
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// One line per group of commands that write and move alike.
+    pub labels: Vec<String>,
+}
+
+pub struct Diagram {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Diagram {
+    /// Lays the states out in columns by their distance from `start`, unreachable ones last.
+    pub fn new(commands: &[MultiCommand], start: &str, blank: char) -> Self {
+        let states = bfs_states(commands, start);
+        let index: HashMap<&str, usize> = states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| (state.as_str(), i))
+            .collect();
+        let mut depths: Vec<Option<usize>> = vec![None; states.len()];
+        depths[0] = Some(0);
+        for (i, state) in states.iter().enumerate() {
+            let Some(depth) = depths[i] else {
+                continue;
+            };
+            for cmd in commands.iter().filter(|cmd| &cmd.istate == state) {
+                if let Some(&j) = index.get(cmd.ostate.as_str()) {
+                    depths[j].get_or_insert(depth + 1);
+                }
+            }
+        }
+        let last = depths.iter().flatten().max().map_or(0, |&depth| depth + 1);
+        let columns: Vec<_> = depths.iter().map(|depth| depth.unwrap_or(last)).collect();
+        let mut heights = HashMap::new();
+        for &column in &columns {
+            *heights.entry(column).or_insert(0) += 1;
+        }
+        let mut rows = HashMap::new();
+        let nodes = states
+            .iter()
+            .zip(&columns)
+            .map(|(state, &column)| {
+                let row = rows.entry(column).or_insert(0);
+                let y = (*row as f32 - (heights[&column] - 1) as f32 / 2.0) * ROW_HEIGHT;
+                *row += 1;
+                Node {
+                    state: state.to_owned(),
+                    column,
+                    pos: pos2(column as f32 * COLUMN_WIDTH, y),
+                    is_start: state == start,
+                    is_halting: !commands.iter().any(|cmd| &cmd.istate == state),
+                }
+            })
+            .collect();
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut groups: Vec<Vec<(String, Vec<String>)>> = Vec::new();
+        for cmd in commands {
+            let (Some(&from), Some(&to)) = (
+                index.get(cmd.istate.as_str()),
+                index.get(cmd.ostate.as_str()),
+            ) else {
+                continue;
+            };
+            let n = match edges.iter().position(|e| e.from == from && e.to == to) {
+                Some(n) => n,
+                None => {
+                    edges.push(Edge {
+                        from,
+                        to,
+                        labels: Vec::new(),
+                    });
+                    groups.push(Vec::new());
+                    edges.len() - 1
+                }
+            };
+            let ocells: Vec<_> = cmd.ocells().iter().map(|x| x.name(blank)).collect();
+            let directions: String = cmd.directions().iter().map(|d| d.to_string()).collect();
+            let action = format!("{},{directions}", tuple(&ocells));
+            let icells: Vec<_> = cmd.icells().iter().map(|x| x.name(blank)).collect();
+            let read = tuple(&icells);
+            match groups[n].iter_mut().find(|(x, _)| *x == action) {
+                Some((_, reads)) => reads.push(read),
+                None => groups[n].push((action, vec![read])),
+            }
+        }
+        for (edge, group) in edges.iter_mut().zip(groups) {
+            edge.labels = group
+                .into_iter()
+                .map(|(action, reads)| format!("{}\u{2192}{action}", reads.join(",")))
+                .collect();
+        }
+        Self { nodes, edges }
+    }
+
+    pub fn is_bent(&self, edge: &Edge) -> bool {
+        let is_reversed = self
+            .edges
+            .iter()
+            .any(|e| e.from == edge.to && e.to == edge.from);
+        is_reversed || self.nodes[edge.to].column != self.nodes[edge.from].column + 1
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph program {\n    rankdir=LR;\n    node [shape=circle];\n");
+        for node in &self.nodes {
+            let shape = if node.is_halting {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            dot += &format!("    {} [shape={shape}];\n", quote(&node.state));
+        }
+        if let Some(node) = self.nodes.iter().find(|x| x.is_start) {
+            // The arrow comes from a point that no state can be mistaken for.
+            let mut start = String::from("__start__");
+            while self.nodes.iter().any(|x| x.state == start) {
+                start += "_";
+            }
+            dot += &format!("    {} [shape=point];\n", quote(&start));
+            dot += &format!("    {} -> {};\n", quote(&start), quote(&node.state));
+        }
+        for edge in &self.edges {
+            dot += &format!(
+                "    {} -> {} [label={}];\n",
+                quote(&self.nodes[edge.from].state),
+                quote(&self.nodes[edge.to].state),
+                quote(&edge.labels.join("\n")),
+            );
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+fn quote(s: &str) -> String {
+    let s = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{s}\"")
+}
+
+fn tuple(names: &[String]) -> String {
+    match names {
+        [name] => name.to_owned(),
+        _ => format!("({})", names.join(",")),
+    }
+}
+
+/// `current` is the state the machine is in and `transition` is the edge it took last.
+pub fn diagram_ui(
+    ui: &mut egui::Ui,
+    diagram: &Diagram,
+    current: Option<&str>,
+    transition: Option<(&str, &str)>,
+    offset: &mut Vec2,
+) {
+    let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::drag());
+    *offset += response.drag_delta();
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let stroke = Stroke::new(1.0, visuals.text_color());
+    let highlight = Stroke::new(2.5, visuals.selection.bg_fill);
+    let font = FontId::proportional(12.0);
+    let origin = rect.left_center() + vec2(2.0 * NODE_RADIUS + 20.0, 0.0) + *offset;
+    let center = |n: usize| origin + diagram.nodes[n].pos.to_vec2();
+    let arrow = |tip: Pos2, direction: Vec2, stroke: Stroke| {
+        let back = tip - direction * 9.0;
+        let side = direction.rot90() * 4.0;
+        painter.add(Shape::convex_polygon(
+            vec![tip, back + side, back - side],
+            stroke.color,
+            Stroke::NONE,
+        ));
+    };
+    for edge in &diagram.edges {
+        let from = &diagram.nodes[edge.from];
+        let to = &diagram.nodes[edge.to];
+        let stroke = if transition == Some((&from.state, &to.state)) {
+            highlight
+        } else {
+            stroke
+        };
+        let text = edge.labels.join("\n");
+        let (p, q) = (center(edge.from), center(edge.to));
+        if edge.from == edge.to {
+            let start = p + vec2(-0.5, -0.87) * NODE_RADIUS;
+            let end = p + vec2(0.5, -0.87) * NODE_RADIUS;
+            let c1 = p + vec2(-1.5, -3.5) * NODE_RADIUS;
+            let c2 = p + vec2(1.5, -3.5) * NODE_RADIUS;
+            painter.add(CubicBezierShape::from_points_stroke(
+                [start, c1, c2, end],
+                false,
+                Color32::TRANSPARENT,
+                stroke,
+            ));
+            arrow(end, (end - c2).normalized(), stroke);
+            let top = p + vec2(0.0, -2.7 * NODE_RADIUS);
+            painter.text(top, Align2::CENTER_BOTTOM, text, font.clone(), stroke.color);
+            continue;
+        }
+        let bend = if diagram.is_bent(edge) { 40.0 } else { 0.0 };
+        let normal = (q - p).normalized().rot90();
+        let control = p + (q - p) / 2.0 + normal * bend * 2.0;
+        let start = p + (control - p).normalized() * NODE_RADIUS;
+        let end = q + (control - q).normalized() * NODE_RADIUS;
+        painter.add(QuadraticBezierShape::from_points_stroke(
+            [start, control, end],
+            false,
+            Color32::TRANSPARENT,
+            stroke,
+        ));
+        arrow(end, (end - control).normalized(), stroke);
+        let middle = start.lerp(control, 0.5).lerp(control.lerp(end, 0.5), 0.5);
+        let align = if normal.y > 0.0 {
+            Align2::CENTER_TOP
+        } else {
+            Align2::CENTER_BOTTOM
+        };
+        painter.text(
+            middle + normal * 4.0,
+            align,
+            text,
+            font.clone(),
+            stroke.color,
+        );
+    }
+    for (n, node) in diagram.nodes.iter().enumerate() {
+        let p = center(n);
+        let (fill, stroke) = if current == Some(&node.state) {
+            (visuals.selection.bg_fill, highlight)
+        } else {
+            (visuals.extreme_bg_color, stroke)
+        };
+        painter.circle(p, NODE_RADIUS, fill, stroke);
+        if node.is_halting {
+            painter.circle_stroke(p, NODE_RADIUS - 4.0, stroke);
+        }
+        if node.is_start {
+            let tip = p - vec2(NODE_RADIUS, 0.0);
+            painter.line_segment([tip - vec2(20.0, 0.0), tip], stroke);
+            arrow(tip, vec2(1.0, 0.0), stroke);
+        }
+        painter.text(
+            p,
+            Align2::CENTER_CENTER,
+            &node.state,
+            font.clone(),
+            visuals.text_color(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_cmd;
+    use crate::turing_machine::cell::Cell;
+    use crate::turing_machine::core::Direction;
+
+    fn commands() -> Vec<MultiCommand> {
+        vec![
+            tm_cmd!("q0", 'a', "q0", 'a', 'R').into(),
+            tm_cmd!("q0", 'b', "q0", 'b', 'R').into(),
+            tm_cmd!("q0", 'c', "q0", 'a', 'R').into(),
+            tm_cmd!("q0", '_', "q1", '_', 'L').into(),
+            tm_cmd!("q1", 'a', "q2", 'a', 'N').into(),
+            tm_cmd!("q3", 'a', "q1", 'a', 'N').into(),
+        ]
+    }
+
+    #[test]
+    fn test_1() {
+        let diagram = Diagram::new(&commands(), "q0", '_');
+        let states: Vec<_> = diagram.nodes.iter().map(|x| x.state.as_str()).collect();
+        assert_eq!(states, ["q0", "q1", "q2", "q3"]);
+        let columns: Vec<_> = diagram.nodes.iter().map(|x| x.column).collect();
+        assert_eq!(columns, [0, 1, 2, 3]);
+        let halting: Vec<_> = diagram.nodes.iter().map(|x| x.is_halting).collect();
+        assert_eq!(halting, [false, false, true, false]);
+        assert!(diagram.nodes[0].is_start);
+        assert!(!diagram.nodes[1].is_start);
+    }
+
+    #[test]
+    fn test_2() {
+        let diagram = Diagram::new(&commands(), "q0", '_');
+        assert_eq!(diagram.edges.len(), 4);
+        assert_eq!((diagram.edges[0].from, diagram.edges[0].to), (0, 0));
+        assert_eq!(diagram.edges[0].labels, ["a,c\u{2192}a,R", "b\u{2192}b,R"]);
+        assert_eq!(diagram.edges[1].labels, ["_\u{2192}_,L"]);
+    }
+
+    #[test]
+    fn test_3() {
+        let commands = vec![MultiCommand::new(
+            "q0".into(),
+            vec!['a'.into(), Cell::Blank],
+            "q1".into(),
+            vec!['b'.into(), 'c'.into()],
+            vec![Direction::Right, Direction::None],
+        )
+        .unwrap()];
+        let diagram = Diagram::new(&commands, "q0", '_');
+        assert_eq!(diagram.edges[0].labels, ["(a,_)\u{2192}(b,c),RN"]);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let commands = vec![
+            tm_cmd!("q0", 'a', "q0", 'b', 'R').into(),
+            tm_cmd!("q0", 'b', "q0", 'b', 'R').into(),
+            tm_cmd!("q0", '_', "q\"1", '_', 'N').into(),
+        ];
+        let dot = Diagram::new(&commands, "q0", '_').to_dot();
+        assert_eq!(
+            dot,
+            "digraph program {
+    rankdir=LR;
+    node [shape=circle];
+    \"q0\" [shape=circle];
+    \"q\\\"1\" [shape=doublecircle];
+    \"__start__\" [shape=point];
+    \"__start__\" -> \"q0\";
+    \"q0\" -> \"q0\" [label=\"a,b\u{2192}b,R\"];
+    \"q0\" -> \"q\\\"1\" [label=\"_\u{2192}_,N\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_start() {
+        let commands = vec![
+            tm_cmd!("start", 'a', "__start__", 'a', 'R').into(),
+            tm_cmd!("__start__", 'a', "start", 'a', 'R').into(),
+        ];
+        let dot = Diagram::new(&commands, "start", '_').to_dot();
+        assert!(dot.contains("    \"__start___\" [shape=point];\n"));
+        assert!(dot.contains("    \"__start___\" -> \"start\";\n"));
+        assert!(!dot.contains("\"start\" -> \"start\""));
+    }
+}
@@ -2,32 +2,40 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-pub struct ExhaustiveWordsIter<'a> {
-    chars: &'a [char],
+use std::fmt::Display;
+
+/// Iterates over all words of the given length, where a word is the concatenation of the
+/// `Display` forms of its letters.
+pub struct ExhaustiveWordsIter<'a, T> {
+    chars: &'a [T],
     current: Vec<usize>,
     length: usize,
     finished: bool,
 }
 
-impl<'a> ExhaustiveWordsIter<'a> {
-    fn new(chars: &'a [char], length: usize) -> Self {
+impl<'a, T> ExhaustiveWordsIter<'a, T> {
+    fn new(chars: &'a [T], length: usize) -> Self {
         Self {
             chars,
             current: vec![0; length],
             length,
-            finished: length == 0,
+            finished: length == 0 || chars.is_empty(),
         }
     }
 }
 
-impl<'a> Iterator for ExhaustiveWordsIter<'a> {
+impl<T: Display> Iterator for ExhaustiveWordsIter<'_, T> {
     type Item = String;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.finished {
             return None;
         }
-        let res: String = self.current.iter().map(|&i| self.chars[i]).collect();
+        let res: String = self
+            .current
+            .iter()
+            .map(|&i| self.chars[i].to_string())
+            .collect();
         for i in (0..self.length).rev() {
             if self.current[i] < self.chars.len() - 1 {
                 self.current[i] += 1;
@@ -40,12 +48,12 @@ impl<'a> Iterator for ExhaustiveWordsIter<'a> {
     }
 }
 
-pub trait GetExhaustiveWords<'a> {
-    fn get_exhaustive_words(&'a self, n: usize) -> ExhaustiveWordsIter<'a>;
+pub trait GetExhaustiveWords<'a, T> {
+    fn get_exhaustive_words(&'a self, n: usize) -> ExhaustiveWordsIter<'a, T>;
 }
 
-impl<'a> GetExhaustiveWords<'a> for [char] {
-    fn get_exhaustive_words(&'a self, n: usize) -> ExhaustiveWordsIter<'a> {
+impl<'a, T: Display> GetExhaustiveWords<'a, T> for [T] {
+    fn get_exhaustive_words(&'a self, n: usize) -> ExhaustiveWordsIter<'a, T> {
         ExhaustiveWordsIter::new(self, n)
     }
 }
@@ -53,6 +61,7 @@ impl<'a> GetExhaustiveWords<'a> for [char] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::turing_machine::symbol::Symbol;
 
     #[test]
     fn test_1() {
@@ -129,4 +138,21 @@ mod tests {
         assert_eq!(i.next().unwrap(), "ccc");
         assert_eq!(i.next(), None);
     }
+
+    #[test]
+    fn test_5() {
+        let s = &[Symbol::from('a'), Symbol::from("X1")];
+        let mut i = s.get_exhaustive_words(2);
+        assert_eq!(i.next().unwrap(), "aa");
+        assert_eq!(i.next().unwrap(), "a{X1}");
+        assert_eq!(i.next().unwrap(), "{X1}a");
+        assert_eq!(i.next().unwrap(), "{X1}{X1}");
+        assert_eq!(i.next(), None);
+    }
+
+    #[test]
+    fn test_6() {
+        let s: &[char] = &[];
+        assert_eq!(s.get_exhaustive_words(2).next(), None);
+    }
 }
@@ -0,0 +1,99 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Undo/redo history of snapshots.
+pub struct History<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    current: T,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    const LIMIT: usize = 200;
+
+    pub fn new(current: T) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            current,
+        }
+    }
+
+    /// Makes `snapshot` the current state if it differs from it.
+    pub fn record(&mut self, snapshot: T) {
+        if snapshot == self.current {
+            return;
+        }
+        self.undo
+            .push(std::mem::replace(&mut self.current, snapshot));
+        if self.undo.len() > Self::LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<T> {
+        let snapshot = self.undo.pop()?;
+        self.redo
+            .push(std::mem::replace(&mut self.current, snapshot));
+        Some(self.current.clone())
+    }
+
+    pub fn redo(&mut self) -> Option<T> {
+        let snapshot = self.redo.pop()?;
+        self.undo
+            .push(std::mem::replace(&mut self.current, snapshot));
+        Some(self.current.clone())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_1() {
+        let mut history = History::new(0);
+        history.record(0);
+        assert!(!history.can_undo());
+        history.record(1);
+        history.record(2);
+        assert_eq!(history.undo(), Some(1));
+        assert_eq!(history.undo(), Some(0));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(1));
+        assert!(history.can_redo());
+    }
+
+    #[test]
+    fn test_2() {
+        let mut history = History::new(0);
+        history.record(1);
+        assert_eq!(history.undo(), Some(0));
+        history.record(2);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(0));
+    }
+
+    #[test]
+    fn test_3() {
+        let mut history = History::new(0);
+        for n in 1..=History::<i32>::LIMIT as i32 + 10 {
+            history.record(n);
+        }
+        let mut n = 0;
+        while history.undo().is_some() {
+            n += 1;
+        }
+        assert_eq!(n, History::<i32>::LIMIT);
+    }
+}
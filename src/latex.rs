@@ -0,0 +1,255 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::io::{self, Write};
+
+use crate::diagram::Diagram;
+use crate::protocol::ProtocolEntry;
+use crate::turing_machine::cell::BLANK_CHAR;
+use crate::turing_machine::core::MultiCommand;
+use crate::turing_machine::tape::TapeSnapshot;
+
+/// Centimeters of the picture per point of the diagram.
+const SCALE: f32 = 0.02;
+
+/// Escapes the text for text mode, writing `blank` as a lambda.
+pub fn escape(s: &str, blank: char) -> String {
+    let mut escaped = String::new();
+    for ch in s.chars() {
+        match ch {
+            _ if ch == blank => escaped.push_str("$\\lambda$"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '^' => escaped.push_str("\\^{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            BLANK_CHAR => escaped.push_str("$\\lambda$"),
+            '\u{2192}' => escaped.push_str("$\\to$"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Escapes text without cells, such as a state name.
+fn escape_text(s: &str) -> String {
+    escape(s, BLANK_CHAR)
+}
+
+/// `header` holds the names of the state, cell and direction columns.
+pub fn commands_tabular(commands: &[MultiCommand], blank: char, header: [&str; 3]) -> String {
+    let num_tapes = commands.first().map_or(1, MultiCommand::len);
+    let [state, cell, dir] = header.map(escape_text);
+    let group = |name: &str| {
+        if num_tapes == 1 {
+            name.to_owned()
+        } else {
+            format!("\\multicolumn{{{num_tapes}}}{{c|}}{{{name}}}")
+        }
+    };
+    let cells = "c|".repeat(num_tapes);
+    let mut tex = format!("\\begin{{tabular}}{{|l|{cells}l|{cells}{cells}}}\n");
+    tex += "\\hline\n";
+    tex += &format!(
+        "{state} & {} & {state} & {} & {} \\\\\n\\hline\n",
+        group(&cell),
+        group(&cell),
+        group(&dir)
+    );
+    for cmd in commands {
+        let mut row = vec![escape_text(&cmd.istate)];
+        row.extend(cmd.icells().iter().map(|x| escape(&x.name(blank), blank)));
+        row.push(escape_text(&cmd.ostate));
+        row.extend(cmd.ocells().iter().map(|x| escape(&x.name(blank), blank)));
+        row.extend(cmd.directions().iter().map(|d| d.to_string()));
+        tex += &row.join(" & ");
+        tex += " \\\\\n";
+    }
+    tex += "\\hline\n\\end{tabular}\n";
+    tex
+}
+
+/// Needs the `automata` library. The labels write blank cells as `blank`.
+pub fn diagram_tikz(diagram: &Diagram, blank: char) -> String {
+    let mut tex =
+        String::from("\\begin{tikzpicture}[->, >=stealth, auto, semithick, node distance=3cm]\n");
+    for (n, node) in diagram.nodes.iter().enumerate() {
+        let mut options = vec!["state"];
+        if node.is_start {
+            options.push("initial");
+        }
+        if node.is_halting {
+            options.push("accepting");
+        }
+        tex += &format!(
+            "  \\node[{}] (s{n}) at ({:.2}, {:.2}) {{{}}};\n",
+            options.join(", "),
+            node.pos.x * SCALE,
+            -node.pos.y * SCALE + 0.0, // Avoids `-0.00`.
+            escape_text(&node.state),
+        );
+    }
+    if !diagram.edges.is_empty() {
+        tex += "  \\path";
+        for edge in &diagram.edges {
+            let edge_options = if edge.from == edge.to {
+                "edge [loop above]"
+            } else if diagram.is_bent(edge) {
+                "edge [bend left]"
+            } else {
+                "edge"
+            };
+            let labels: Vec<_> = edge.labels.iter().map(|x| escape(x, blank)).collect();
+            tex += &format!(
+                "\n    (s{}) {edge_options} node[align=center] {{{}}} (s{})",
+                edge.from,
+                labels.join(" \\\\ "),
+                edge.to,
+            );
+        }
+        tex += ";\n";
+    }
+    tex += "\\end{tikzpicture}\n";
+    tex
+}
+
+fn configuration(tape: &TapeSnapshot, state: &str) -> String {
+    let mut tex = String::new();
+    for (n, cell) in tape.cells.iter().enumerate() {
+        let cell = escape(&cell.to_string_with_blank(tape.blank), tape.blank);
+        if n == tape.head {
+            tex += &format!(
+                "\\underline{{{cell}}}\\textsubscript{{{}}}",
+                escape_text(state)
+            );
+        } else {
+            tex += &cell;
+        }
+    }
+    tex
+}
+
+/// Manual edits are marked with an asterisk.
+pub fn protocol_enumerate(
+    out: &mut impl Write,
+    entries: impl IntoIterator<Item = ProtocolEntry>,
+) -> io::Result<()> {
+    writeln!(out, "\\begin{{enumerate}}")?;
+    for (n, entry) in entries.into_iter().enumerate() {
+        let mark = if entry.is_edit { "$^*$" } else { "" };
+        let tapes: Vec<_> = entry
+            .tapes
+            .iter()
+            .map(|x| configuration(x, &entry.state))
+            .collect();
+        writeln!(out, "  \\item[{n}{mark}.] {}", tapes.join(" \\\\ "))?;
+    }
+    writeln!(out, "\\end{{enumerate}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_cmd;
+    use crate::turing_machine::cell::Cell;
+    use crate::turing_machine::core::Direction;
+    use crate::turing_machine::tape::Tape;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("q_1", BLANK_CHAR), "q\\_1");
+        assert_eq!(escape("{X1}&%", BLANK_CHAR), "\\{X1\\}\\&\\%");
+        assert_eq!(
+            escape("\\^~", BLANK_CHAR),
+            "\\textbackslash{}\\^{}\\textasciitilde{}"
+        );
+        assert_eq!(escape("\u{03BB}\u{2192}a", BLANK_CHAR), "$\\lambda$$\\to$a");
+        assert_eq!(escape("a_", '_'), "a$\\lambda$");
+    }
+
+    #[test]
+    fn test_commands_tabular_1() {
+        let commands = vec![
+            tm_cmd!("q0", 'a', "q1", 'b', 'R').into(),
+            tm_cmd!("q1", Cell::Blank, "q1", 'a', 'N').into(),
+        ];
+        let tex = commands_tabular(&commands, BLANK_CHAR, ["State", "Cell", "Dir"]);
+        assert_eq!(
+            tex,
+            "\\begin{tabular}{|l|c|l|c|c|}
+\\hline
+State & Cell & State & Cell & Dir \\\\
+\\hline
+q0 & a & q1 & b & R \\\\
+q1 & $\\lambda$ & q1 & a & N \\\\
+\\hline
+\\end{tabular}
+"
+        );
+    }
+
+    #[test]
+    fn test_commands_tabular_2() {
+        let commands = vec![MultiCommand::new(
+            "q0".into(),
+            vec!['a'.into(), Cell::Blank],
+            "q1".into(),
+            vec!['b'.into(), 'c'.into()],
+            vec![Direction::Right, Direction::None],
+        )
+        .unwrap()];
+        let tex = commands_tabular(&commands, '_', ["S", "C", "D"]);
+        assert!(tex.starts_with("\\begin{tabular}{|l|c|c|l|c|c|c|c|}\n"));
+        assert!(tex.contains(
+            "S & \\multicolumn{2}{c|}{C} & S & \\multicolumn{2}{c|}{C} & \\multicolumn{2}{c|}{D}"
+        ));
+        assert!(tex.contains("q0 & a & $\\lambda$ & q1 & b & c & R & N \\\\\n"));
+    }
+
+    #[test]
+    fn test_diagram_tikz() {
+        let commands = vec![
+            tm_cmd!("q0", 'a', "q0", 'a', 'R').into(),
+            tm_cmd!("q0", 'b', "q1", Cell::Blank, 'L').into(),
+        ];
+        let tex = diagram_tikz(&Diagram::new(&commands, "q0", '_'), '_');
+        assert_eq!(
+            tex,
+            "\\begin{tikzpicture}[->, >=stealth, auto, semithick, node distance=3cm]
+  \\node[state, initial] (s0) at (0.00, 0.00) {q0};
+  \\node[state, accepting] (s1) at (3.40, 0.00) {q1};
+  \\path
+    (s0) edge [loop above] node[align=center] {a$\\to$a,R} (s0)
+    (s0) edge node[align=center] {b$\\to$$\\lambda$,L} (s1);
+\\end{tikzpicture}
+"
+        );
+    }
+
+    #[test]
+    fn test_protocol_enumerate() {
+        let mut tape = Tape::with_blank("ab", '_');
+        let entry = |state: &str, tape: &Tape, is_edit| ProtocolEntry {
+            state: state.into(),
+            tapes: vec![tape.snapshot()],
+            command: None,
+            is_edit,
+        };
+        let first = entry("q0", &tape, false);
+        tape.left();
+        let second = entry("q_1", &tape, true);
+        let mut tex = Vec::new();
+        protocol_enumerate(&mut tex, [first, second]).unwrap();
+        assert_eq!(
+            String::from_utf8(tex).unwrap(),
+            "\\begin{enumerate}
+  \\item[0.] \\underline{a}\\textsubscript{q0}b
+  \\item[1$^*$.] \\underline{$\\lambda$}\\textsubscript{q\\_1}ab
+\\end{enumerate}
+"
+        );
+    }
+}
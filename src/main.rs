@@ -2,33 +2,60 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::Write;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    mpsc, Arc, Mutex,
 };
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
 use eframe::egui::text::LayoutJob;
 use eframe::egui::{
-    self, popup_below_widget, Align, ComboBox, Grid, Layout, RichText, ScrollArea, Sides, Style,
-    WidgetText,
+    self, popup_below_widget, Align, Color32, ComboBox, Grid, Layout, RichText, ScrollArea, Sides,
+    Style, WidgetText,
 };
 use egui_extras::{Column, Size, StripBuilder, TableBuilder};
 use egui_plot::{Legend, Line, Plot};
 use fluent::{FluentBundle, FluentResource};
 
+mod coverage;
+mod diagram;
 mod exhaustive_words;
+mod history;
+mod latex;
+mod program_file;
+mod protocol;
+mod rows;
+mod space_time;
 mod translations;
 mod turing_machine;
 
+use self::coverage::{add_fires, Coverage};
+use self::diagram::{diagram_ui, Diagram};
 use self::exhaustive_words::GetExhaustiveWords;
+use self::history::History;
+use self::latex::{commands_tabular, diagram_tikz, protocol_enumerate};
+use self::program_file::{format_command, is_state_name_valid, parse_command, ProgramFile};
+use self::protocol::{
+    write_protocol, Protocol, ProtocolEntry, ProtocolFormat, ProtocolWindow, Trajectory,
+};
+use self::rows::{duplicate_rows, move_rows, natural_cmp, remove_rows};
+use self::space_time::{SpaceTime, CELL_SIZE, MAX_ROWS};
 use self::translations::AppLanguage;
+use self::turing_machine::binary::Binary;
 use self::turing_machine::cell::{Cell, BLANK_CHAR};
-use self::turing_machine::core::{Direction, MultiCommand};
+use self::turing_machine::core::{
+    bfs_states, build_program_with_classes, expand_commands, rename_states, CellPattern, CellWrite,
+    Direction, MultiCommand, Program, SymbolClass,
+};
+use self::turing_machine::single_tape::{product, SingleTape, SEPARATOR};
+use self::turing_machine::stats::{Crossings, HaltReason};
+use self::turing_machine::symbol::{split_names, Symbol};
 use self::turing_machine::TuringMachine;
 
 #[derive(Clone, PartialEq)]
@@ -65,24 +92,112 @@ impl From<Preset> for WidgetText {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum CommandsView {
+    Table,
+    Expanded,
+    Matrix,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ChartView {
+    Plot,
+    Trajectory,
+    Diagram,
+    Statistics,
+    Crossings,
+    SpaceTime,
+}
+
+/// Space-time diagram uploaded to the GPU, with the rows and generation of the protocol it was
+/// made of.
+struct SpaceTimeTexture {
+    rows: usize,
+    generation: usize,
+    texture: egui::TextureHandle,
+    states: Vec<(String, Color32)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FilterField {
+    All,
+    State,
+    Read,
+    Write,
+    Direction,
+}
+
+/// Progress of the run thread, which is reported at most once per [`RunUpdate::PERIOD`].
+struct RunUpdate {
+    /// Steps made since the start or continuation of the run.
+    steps: usize,
+    latest: ProtocolEntry,
+    elapsed: Duration,
+}
+
+impl RunUpdate {
+    const PERIOD: Duration = Duration::from_millis(50);
+
+    fn steps_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
 struct Application {
     pixels_per_point: f32,
-    tm_alphabet_primary: String,
-    tm_alphabet_secondary: String,
-    tm_input: String,
+    tm_alphabet_primary: Vec<Symbol>,
+    tm_alphabet_secondary: Vec<Symbol>,
+    tm_new_symbol_primary: String,
+    tm_new_symbol_secondary: String,
+    tm_blank: char,
+    tm_classes: Vec<SymbolClass>,
+    tm_commands_view: CommandsView,
+    tm_matrix_cell: Option<(String, Vec<Cell>)>,
+    tm_inputs: Vec<String>,
+    tm_heads: Vec<isize>,
+    tm_start_state: String,
     is_tm_running: Arc<AtomicBool>,
     is_tm_stop_requested: Arc<AtomicBool>,
     is_tm_plotting: Arc<AtomicBool>,
     is_tm_stop_plot_requested: Arc<AtomicBool>,
     tm_preset: Preset,
+    tm_pending_preset: Option<Preset>,
+    history: History<ProgramFile>,
+    is_editing_text: bool,
+    saved_program: ProgramFile,
     num_tapes: usize,
     tm_commands: Vec<MultiCommand>,
-    tm_protocol: Arc<Mutex<Vec<Vec<String>>>>,
+    tm_selected_commands: BTreeSet<usize>,
+    tm_selection_anchor: Option<usize>,
+    tm_filter: String,
+    tm_filter_field: FilterField,
+    tm_rename_from: String,
+    tm_rename_to: String,
+    paste_text: Option<String>,
+    paste_msg: String,
+    tm_protocol: Arc<Mutex<Protocol>>,
+    tm_protocol_streamed: bool,
     tm_protocol_reversed: bool,
+    tm_updates: Option<mpsc::Receiver<RunUpdate>>,
+    tm_run_status: Option<RunUpdate>,
+    tm_chart_view: ChartView,
+    tm_diagram_offset: egui::Vec2,
+    tm_space_time: Option<SpaceTimeTexture>,
+    tm_trajectory: Trajectory,
+    tm_protocol_window: ProtocolWindow,
     tm_plot_points: Arc<Mutex<Vec<[f64; 2]>>>,
+    tm_plot_crossings: Arc<Mutex<Vec<(usize, usize)>>>,
+    tm_coverage: Coverage,
+    tm_run_commands: Vec<MultiCommand>,
+    tm_plot_commands: Vec<MultiCommand>,
     save_protocol_msg: String,
-    tm_thread: Option<JoinHandle<()>>,
-    tm_plot_thread: Option<JoinHandle<()>>,
+    export_msg: String,
+    program_file_msg: String,
+    run_msg: String,
+    tm: Option<TuringMachine>,
+    tm_edit_cell: Option<(usize, isize)>,
+    tm_thread: Option<JoinHandle<TuringMachine>>,
+    tm_plot_thread: Option<JoinHandle<Vec<usize>>>,
     language: AppLanguage,
     messages: FluentBundle<FluentResource>,
 }
@@ -92,27 +207,67 @@ impl Application {
 
     pub fn new(pixels_per_point: f32) -> Self {
         let language = AppLanguage::default();
-        Self {
+        let mut app = Self {
             pixels_per_point,
-            tm_alphabet_primary: "abc".into(),
-            tm_alphabet_secondary: "01ABC".into(),
-            tm_input: "".into(),
+            tm_alphabet_primary: Self::symbols("abc"),
+            tm_alphabet_secondary: Self::symbols("01ABC"),
+            tm_new_symbol_primary: "".into(),
+            tm_new_symbol_secondary: "".into(),
+            tm_blank: BLANK_CHAR,
+            tm_classes: Vec::new(),
+            tm_commands_view: CommandsView::Table,
+            tm_matrix_cell: None,
+            tm_inputs: vec!["".into()],
+            tm_heads: vec![0],
+            tm_start_state: "q0".into(),
             is_tm_running: Arc::new(AtomicBool::new(false)),
             is_tm_stop_requested: Arc::new(AtomicBool::new(false)),
             is_tm_plotting: Arc::new(AtomicBool::new(false)),
             is_tm_stop_plot_requested: Arc::new(AtomicBool::new(false)),
             tm_preset: Preset::OneTape,
+            tm_pending_preset: None,
+            history: History::new(ProgramFile::default()),
+            is_editing_text: false,
+            saved_program: ProgramFile::default(),
             num_tapes: 1,
             tm_commands: Self::preset_one_tape(),
-            tm_protocol: Arc::new(Mutex::new(Vec::new())),
+            tm_selected_commands: BTreeSet::new(),
+            tm_selection_anchor: None,
+            tm_filter: "".into(),
+            tm_filter_field: FilterField::All,
+            tm_rename_from: "".into(),
+            tm_rename_to: "".into(),
+            paste_text: None,
+            paste_msg: "".into(),
+            tm_protocol: Arc::new(Mutex::new(Protocol::default())),
+            tm_protocol_streamed: false,
             tm_protocol_reversed: true,
+            tm_updates: None,
+            tm_run_status: None,
+            tm_chart_view: ChartView::Plot,
+            tm_diagram_offset: egui::Vec2::ZERO,
+            tm_space_time: None,
+            tm_trajectory: Trajectory::default(),
+            tm_protocol_window: ProtocolWindow::default(),
             tm_plot_points: Arc::new(Mutex::new(Vec::new())),
+            tm_plot_crossings: Arc::new(Mutex::new(Vec::new())),
+            tm_coverage: Coverage::default(),
+            tm_run_commands: Vec::new(),
+            tm_plot_commands: Vec::new(),
             save_protocol_msg: "".into(),
+            export_msg: "".into(),
+            program_file_msg: "".into(),
+            run_msg: "".into(),
+            tm: None,
+            tm_edit_cell: None,
             tm_thread: None,
             tm_plot_thread: None,
             messages: language.get_bundle(),
             language,
-        }
+        };
+        app.history = History::new(app.to_program_file());
+        app.saved_program = app.to_program_file();
+        app
     }
 
     fn preset_one_tape() -> Vec<MultiCommand> {
@@ -300,20 +455,245 @@ impl Application {
 
     fn set_preset(&mut self) {
         (*self.tm_protocol.lock().unwrap()).clear();
-        self.tm_alphabet_primary = "abc".into();
-        self.tm_input.clear();
+        self.tm = None;
+        self.tm_alphabet_primary = Self::symbols("abc");
+        self.tm_blank = BLANK_CHAR;
+        self.tm_classes.clear();
+        self.clear_selection();
         match self.tm_preset {
             Preset::OneTape => {
                 self.num_tapes = 1;
-                self.tm_alphabet_secondary = "01ABC".into();
+                self.tm_alphabet_secondary = Self::symbols("01ABC");
                 self.tm_commands = Self::preset_one_tape();
             }
             Preset::MultiTape => {
                 self.num_tapes = 2;
-                self.tm_alphabet_secondary = "01X".into();
+                self.tm_alphabet_secondary = Self::symbols("01X");
                 self.tm_commands = Self::preset_multitape();
             }
         }
+        self.tm_inputs = vec!["".into(); self.num_tapes];
+        self.tm_heads = vec![0; self.num_tapes];
+        self.tm_start_state = "q0".into();
+        self.saved_program = self.to_program_file();
+    }
+
+    fn symbols(s: &str) -> Vec<Symbol> {
+        s.chars().map(Symbol::from).collect()
+    }
+
+    fn set_blank(&mut self, blank: char) {
+        let symbol = Symbol::from(blank);
+        if blank.is_whitespace()
+            || Symbol::parse(symbol.name()).is_err()
+            || self.tm_alphabet_primary.contains(&symbol)
+            || self.tm_alphabet_secondary.contains(&symbol)
+        {
+            return;
+        }
+        self.convert_inputs(blank);
+        self.tm_blank = blank;
+        (*self.tm_protocol.lock().unwrap()).clear();
+        self.tm = None;
+    }
+
+    fn convert_inputs(&mut self, blank: char) {
+        for input in self.tm_inputs.iter_mut() {
+            *input = split_names(input)
+                .into_iter()
+                .map(|name| Cell::from_name(name, self.tm_blank).to_string_with_blank(blank))
+                .collect();
+        }
+    }
+
+    fn alphabet_ui(&mut self, ui: &mut egui::Ui, is_primary: bool, interactive: bool) {
+        let label_remove = self.msg("symbol-remove");
+        let label_new = self.msg("symbol-new");
+        let (alphabet, other, new_symbol) = if is_primary {
+            (
+                &mut self.tm_alphabet_primary,
+                &self.tm_alphabet_secondary,
+                &mut self.tm_new_symbol_primary,
+            )
+        } else {
+            (
+                &mut self.tm_alphabet_secondary,
+                &self.tm_alphabet_primary,
+                &mut self.tm_new_symbol_secondary,
+            )
+        };
+        ui.add_enabled_ui(interactive, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                let mut removed = None;
+                for (n, symbol) in alphabet.iter().enumerate() {
+                    if ui
+                        .button(format!("{} \u{00D7}", symbol.name()))
+                        .on_hover_text(&label_remove)
+                        .clicked()
+                    {
+                        removed = Some(n);
+                    }
+                }
+                if let Some(n) = removed {
+                    alphabet.remove(n);
+                }
+                let response = ui.add(
+                    egui::widgets::TextEdit::singleline(new_symbol)
+                        .desired_width(40.0)
+                        .hint_text(label_new),
+                );
+                let is_entered =
+                    response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("+").clicked() || is_entered {
+                    let is_blank = new_symbol.chars().eq([self.tm_blank]);
+                    if let Ok(symbol) = Symbol::parse(new_symbol.trim()) {
+                        if !is_blank && !alphabet.contains(&symbol) && !other.contains(&symbol) {
+                            alphabet.push(symbol);
+                            new_symbol.clear();
+                        }
+                    }
+                }
+            });
+        });
+    }
+
+    fn classes_ui(&mut self, ui: &mut egui::Ui, interactive: bool) {
+        let label_remove = self.msg("class-remove");
+        let label_add = self.msg("class-add");
+        let cells = self.cells();
+        ui.add_enabled_ui(interactive, |ui| {
+            ui.vertical(|ui| {
+                let mut removed = None;
+                for n in 0..self.tm_classes.len() {
+                    ui.horizontal_wrapped(|ui| {
+                        let mut name = self.tm_classes[n].name.to_owned();
+                        let response = ui.add(
+                            egui::widgets::TextEdit::singleline(&mut name).desired_width(60.0),
+                        );
+                        let is_unique = self.tm_classes.iter().all(|c| c.name != name);
+                        if response.changed() && SymbolClass::is_name_valid(&name) && is_unique {
+                            self.rename_class(n, name);
+                        }
+                        let class = &mut self.tm_classes[n];
+                        for &cell in &cells {
+                            let mut is_member = class.cells.contains(&cell);
+                            if ui
+                                .toggle_value(&mut is_member, cell.name(self.tm_blank))
+                                .changed()
+                            {
+                                // Keep the order of the alphabets, as class writes map cells by
+                                // their positions.
+                                class.cells = cells
+                                    .iter()
+                                    .copied()
+                                    .filter(|&c| {
+                                        if c == cell {
+                                            is_member
+                                        } else {
+                                            class.cells.contains(&c)
+                                        }
+                                    })
+                                    .collect();
+                            }
+                        }
+                        if ui.button("\u{00D7}").on_hover_text(&label_remove).clicked() {
+                            removed = Some(n);
+                        }
+                    });
+                }
+                if let Some(n) = removed {
+                    self.tm_classes.remove(n);
+                }
+                if ui.button("+").on_hover_text(label_add).clicked() {
+                    let name = (1..)
+                        .map(|n| format!("C{n}"))
+                        .find(|name| self.tm_classes.iter().all(|c| &c.name != name))
+                        .unwrap();
+                    self.tm_classes.push(SymbolClass {
+                        name,
+                        cells: Vec::new(),
+                    });
+                }
+            });
+        });
+    }
+
+    /// Renames the class and the commands reading or writing it.
+    fn rename_class(&mut self, n: usize, name: String) {
+        let old = std::mem::replace(&mut self.tm_classes[n].name, name.to_owned());
+        for cmd in self.tm_commands.iter_mut() {
+            for i in 0..cmd.len() {
+                let icell = cmd.get_mut_icell(i).unwrap();
+                if *icell == CellPattern::Class(old.to_owned()) {
+                    *icell = CellPattern::Class(name.to_owned());
+                }
+                let ocell = cmd.get_mut_ocell(i).unwrap();
+                if *ocell == CellWrite::Class(old.to_owned()) {
+                    *ocell = CellWrite::Class(name.to_owned());
+                }
+            }
+        }
+    }
+
+    fn cells(&self) -> Vec<Cell> {
+        [Cell::Blank]
+            .into_iter()
+            .chain(self.tm_alphabet_primary.iter().map(|&s| s.into()))
+            .chain(self.tm_alphabet_secondary.iter().map(|&s| s.into()))
+            .collect()
+    }
+
+    /// Drops the symbols of the input that are not allowed on the tape, but keeps an unfinished
+    /// multi-character symbol at the end.
+    fn filter_input(&self, tape: usize) -> String {
+        let input = &self.tm_inputs[tape];
+        let unfinished = match input.rfind(Symbol::OPEN) {
+            Some(n) if !input[n..].contains(Symbol::CLOSE) => &input[n..],
+            _ => "",
+        };
+        let mut res: String = split_names(&input[..input.len() - unfinished.len()])
+            .into_iter()
+            .map(|name| Cell::from_name(name, self.tm_blank))
+            .filter(|cell| match cell {
+                // Blanks are allowed to describe a configuration in the middle of a run.
+                Cell::Blank => true,
+                // Auxiliary tapes may be pre-loaded with secondary symbols too.
+                Cell::Symbol(symbol) => {
+                    self.tm_alphabet_primary.contains(symbol)
+                        || (tape != 0 && self.tm_alphabet_secondary.contains(symbol))
+                }
+            })
+            .map(|cell| cell.to_string_with_blank(self.tm_blank))
+            .collect();
+        res.push_str(unfinished);
+        res
+    }
+
+    /// Returns all states mentioned in the commands in order of first appearance.
+    fn states(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut states = Vec::new();
+        for cmd in &self.tm_commands {
+            for state in [&cmd.istate, &cmd.ostate] {
+                if !state.is_empty() && seen.insert(state) {
+                    states.push(state.to_owned());
+                }
+            }
+        }
+        states
+    }
+
+    /// Falls back to the first state if no command mentions the start state.
+    fn check_start_state(&mut self) -> Result<()> {
+        let states = self.states();
+        let Some(first) = states.first() else {
+            return Ok(());
+        };
+        if states.contains(&self.tm_start_state) {
+            return Ok(());
+        }
+        let missing = std::mem::replace(&mut self.tm_start_state, first.to_owned());
+        Err(anyhow!(self.msg("err-no-start-state") + " " + &missing))
     }
 
     fn msg(&self, m: &str) -> String {
@@ -343,58 +723,94 @@ impl Application {
             if ui.button(self.msg("btn-change-language")).clicked() {
                 self.next_lang();
             }
+            ui.separator();
+            ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
+                let button_undo = egui::Button::new(self.msg("undo"));
+                if ui
+                    .add_enabled(self.history.can_undo(), button_undo)
+                    .clicked()
+                {
+                    self.undo();
+                }
+                let button_redo = egui::Button::new(self.msg("redo"));
+                if ui
+                    .add_enabled(self.history.can_redo(), button_redo)
+                    .clicked()
+                {
+                    self.redo();
+                }
+            });
+            if self.is_program_modified() {
+                ui.label(RichText::new(self.msg("label-modified")).weak());
+            }
         });
         Grid::new("grid_alphabet_input")
             .num_columns(2)
             .spacing([40.0, 4.0])
             .show(ui, |ui| {
                 ui.label(self.msg("alphabet-primary"));
-                let widget = egui::widgets::TextEdit::singleline(&mut self.tm_alphabet_primary);
-                let response = ui.add(if is_tm_running || is_tm_plotting {
-                    widget.interactive(false)
-                } else {
-                    widget
-                });
-                if response.changed() {
-                    let mut seen = HashSet::<char>::from_iter(self.tm_alphabet_secondary.chars());
-                    self.tm_alphabet_primary = self
-                        .tm_alphabet_primary
-                        .chars()
-                        .filter(|&ch| seen.insert(ch) && ch != BLANK_CHAR)
-                        .collect();
-                }
+                self.alphabet_ui(ui, true, !is_tm_running && !is_tm_plotting);
                 ui.end_row();
 
                 ui.label(self.msg("alphabet-secondary"));
-                let widget = egui::widgets::TextEdit::singleline(&mut self.tm_alphabet_secondary);
+                self.alphabet_ui(ui, false, !is_tm_running && !is_tm_plotting);
+                ui.end_row();
+
+                ui.label(self.msg("blank"));
+                let mut blank = self.tm_blank.to_string();
+                let widget = egui::widgets::TextEdit::singleline(&mut blank).desired_width(20.0);
                 let response = ui.add(if is_tm_running || is_tm_plotting {
                     widget.interactive(false)
                 } else {
                     widget
                 });
                 if response.changed() {
-                    let mut seen = HashSet::<char>::from_iter(self.tm_alphabet_primary.chars());
-                    self.tm_alphabet_secondary = self
-                        .tm_alphabet_secondary
-                        .chars()
-                        .filter(|&ch| seen.insert(ch) && ch != BLANK_CHAR)
-                        .collect();
+                    // Keep the last typed character.
+                    if let Some(ch) = blank.chars().last() {
+                        self.set_blank(ch);
+                    }
                 }
                 ui.end_row();
 
-                ui.label(self.msg("input"));
-                let widget = egui::widgets::TextEdit::singleline(&mut self.tm_input);
-                let response = ui.add(if is_tm_running {
-                    widget.interactive(false)
-                } else {
-                    widget
+                ui.label(self.msg("classes"));
+                self.classes_ui(ui, !is_tm_running && !is_tm_plotting);
+                ui.end_row();
+
+                ui.label(self.msg("start-state"));
+                ui.add_enabled_ui(!is_tm_running, |ui| {
+                    ComboBox::from_id_salt("start_state")
+                        .selected_text(&self.tm_start_state)
+                        .show_ui(ui, |ui| {
+                            for state in self.states() {
+                                ui.selectable_value(
+                                    &mut self.tm_start_state,
+                                    state.to_owned(),
+                                    state,
+                                );
+                            }
+                        });
                 });
-                if response.changed() {
-                    self.tm_input = self
-                        .tm_input
-                        .chars()
-                        .filter(|&ch| self.tm_alphabet_primary.contains(ch))
-                        .collect();
+                ui.end_row();
+
+                for i in 0..self.num_tapes {
+                    ui.label(format!("{} {}", self.msg("input"), i + 1));
+                    ui.horizontal(|ui| {
+                        let widget = egui::widgets::TextEdit::singleline(&mut self.tm_inputs[i]);
+                        let response = ui.add(if is_tm_running {
+                            widget.interactive(false)
+                        } else {
+                            widget
+                        });
+                        if response.changed() {
+                            self.tm_inputs[i] = self.filter_input(i);
+                        }
+                        ui.label(self.msg("head"));
+                        ui.add_enabled(
+                            !is_tm_running,
+                            egui::widgets::DragValue::new(&mut self.tm_heads[i]).speed(0.1),
+                        );
+                    });
+                    ui.end_row();
                 }
             });
         ui.horizontal(|ui| {
@@ -407,6 +823,34 @@ impl Application {
                         self.remove_command();
                     }
                 });
+                ui.vertical(|ui| {
+                    let has_selection = !self.tm_selected_commands.is_empty();
+                    if ui
+                        .add_enabled(
+                            has_selection,
+                            egui::Button::new(self.msg("command-duplicate")),
+                        )
+                        .clicked()
+                    {
+                        self.duplicate_commands();
+                    }
+                    if ui.button(self.msg("commands-sort")).clicked() {
+                        self.sort_commands();
+                    }
+                });
+                ui.vertical(|ui| {
+                    let has_selection = !self.tm_selected_commands.is_empty();
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new(self.msg("commands-copy")))
+                        .clicked()
+                    {
+                        ui.ctx().copy_text(self.selected_commands_text());
+                    }
+                    if ui.button(self.msg("commands-paste")).clicked() {
+                        self.paste_text = Some("".into());
+                        self.paste_msg.clear();
+                    }
+                });
                 ui.vertical(|ui| {
                     if ui.button(self.msg("tape-add")).clicked() {
                         self.add_tape();
@@ -414,6 +858,20 @@ impl Application {
                     if ui.button(self.msg("tape-remove")).clicked() {
                         self.remove_tape();
                     }
+                    if ui
+                        .add_enabled(
+                            self.num_tapes > 1,
+                            egui::Button::new(self.msg("tape-to-one")),
+                        )
+                        .clicked()
+                    {
+                        let res = self.compile_to_one_tape();
+                        self.set_run_msg(res);
+                    }
+                    if ui.button(self.msg("alphabet-to-binary")).clicked() {
+                        let res = self.compile_to_binary();
+                        self.set_run_msg(res);
+                    }
                 });
             });
             ui.vertical(|ui| {
@@ -424,8 +882,35 @@ impl Application {
                         }
                         ui.spinner();
                     });
-                } else if ui.button(self.msg("start")).clicked() {
-                    self.start_tm(ui.ctx());
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button(self.msg("start")).clicked() {
+                            let res = self.start_tm(ui.ctx());
+                            self.set_run_msg(res);
+                        }
+                        if self.tm.is_some() && ui.button(self.msg("continue")).clicked() {
+                            let res = self.continue_tm(ui.ctx());
+                            self.set_run_msg(res);
+                        }
+                    });
+                }
+                if let Some(status) = &self.tm_run_status {
+                    ui.label(format!(
+                        "{}: {} ({:.0} {})",
+                        self.msg("label-steps"),
+                        status.steps,
+                        status.steps_per_second(),
+                        self.msg("label-per-second"),
+                    ));
+                }
+                let label = self.msg("protocol-stream");
+                ui.add_enabled(
+                    !is_tm_running,
+                    egui::Checkbox::new(&mut self.tm_protocol_streamed, label),
+                )
+                .on_hover_text(self.msg("protocol-stream-hint"));
+                if !self.run_msg.is_empty() {
+                    ui.colored_label(ui.visuals().error_fg_color, &self.run_msg);
                 }
                 ui.add_enabled_ui(!is_tm_running, |ui| {
                     let button_save_protocol = ui.button(self.msg("protocol-save"));
@@ -449,6 +934,72 @@ impl Application {
                         },
                     );
                 });
+                let popup_export_id = egui::Id::new("popup_export_id");
+                let menu_export = ui.menu_button(self.msg("export"), |ui| {
+                    let exports = [
+                        ("export-dot", Self::export_dot as fn(&Self) -> _),
+                        ("export-latex-table", Self::export_latex_table),
+                        ("export-tikz", Self::export_tikz),
+                        ("export-latex-protocol", Self::export_latex_protocol),
+                        ("export-space-time", Self::export_space_time),
+                    ];
+                    for (label, export) in exports {
+                        if ui.button(self.msg(label)).clicked() {
+                            ui.close_menu();
+                            return Some(self.check_start_state().and_then(|_| export(self)));
+                        }
+                    }
+                    None
+                });
+                if let Some(Some(res)) = menu_export.inner {
+                    self.export_msg = match res {
+                        Ok(_) => self.msg("ok-file-saved"),
+                        Err(e) => format!("{e}"),
+                    };
+                    ui.memory_mut(|mem| mem.toggle_popup(popup_export_id));
+                }
+                popup_below_widget(
+                    ui,
+                    popup_export_id,
+                    &menu_export.response,
+                    egui::PopupCloseBehavior::CloseOnClick,
+                    |ui| {
+                        ui.set_min_width(400.0);
+                        ui.label(&self.export_msg);
+                    },
+                );
+            });
+            ui.vertical(|ui| {
+                let popup_program_file_id = egui::Id::new("popup_program_file_id");
+                let button_save_program = ui.button(self.msg("program-save"));
+                if button_save_program.clicked() {
+                    self.program_file_msg = match self.save_program() {
+                        Ok(_) => self.msg("ok-file-saved"),
+                        Err(e) => format!("{e:#}"),
+                    };
+                    ui.memory_mut(|mem| mem.toggle_popup(popup_program_file_id));
+                }
+                let button_load_program = ui.add_enabled(
+                    !is_tm_running && !is_tm_plotting,
+                    egui::Button::new(self.msg("program-load")),
+                );
+                if button_load_program.clicked() {
+                    self.program_file_msg = match self.load_program() {
+                        Ok(_) => self.msg("ok-file-loaded"),
+                        Err(e) => format!("{e:#}"),
+                    };
+                    ui.memory_mut(|mem| mem.toggle_popup(popup_program_file_id));
+                }
+                popup_below_widget(
+                    ui,
+                    popup_program_file_id,
+                    &button_load_program,
+                    egui::PopupCloseBehavior::CloseOnClick,
+                    |ui| {
+                        ui.set_min_width(400.0);
+                        ui.label(&self.program_file_msg);
+                    },
+                );
             });
             if is_tm_plotting {
                 if ui.button(self.msg("plotting-stop")).clicked() {
@@ -456,37 +1007,85 @@ impl Application {
                 }
                 ui.spinner();
             } else if ui.button(self.msg("plotting-start")).clicked() {
-                self.start_plot(ui.ctx());
+                let res = self.start_plot(ui.ctx());
+                self.set_run_msg(res);
             }
         });
-        ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
-            ComboBox::from_label(self.msg("label-presets"))
-                .selected_text(self.tm_preset.clone())
-                .show_ui(ui, |ui| {
-                    let response1 =
-                        ui.selectable_value(&mut self.tm_preset, Preset::OneTape, Preset::OneTape);
-                    let response2 = ui.selectable_value(
-                        &mut self.tm_preset,
-                        Preset::MultiTape,
-                        Preset::MultiTape,
-                    );
-                    if response1.clicked() || response2.clicked() {
-                        self.set_preset();
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
+                let old_preset = self.tm_preset.clone();
+                ComboBox::from_label(self.msg("label-presets"))
+                    .selected_text(self.tm_preset.clone())
+                    .show_ui(ui, |ui| {
+                        let response1 = ui.selectable_value(
+                            &mut self.tm_preset,
+                            Preset::OneTape,
+                            Preset::OneTape,
+                        );
+                        let response2 = ui.selectable_value(
+                            &mut self.tm_preset,
+                            Preset::MultiTape,
+                            Preset::MultiTape,
+                        );
+                        if response1.clicked() || response2.clicked() {
+                            if self.is_program_modified() {
+                                let preset = std::mem::replace(&mut self.tm_preset, old_preset);
+                                self.tm_pending_preset = Some(preset);
+                            } else {
+                                self.set_preset();
+                            }
+                        }
+                    });
+            });
+            ui.separator();
+            for (view, label) in [
+                (CommandsView::Table, "view-table"),
+                (CommandsView::Expanded, "view-expanded"),
+                (CommandsView::Matrix, "view-matrix"),
+            ] {
+                let label = self.msg(label);
+                ui.selectable_value(&mut self.tm_commands_view, view, label);
+            }
+            ui.separator();
+            match self.tm_coverage.percentage(&self.tm_commands) {
+                Some(percentage) => {
+                    ui.label(format!("{} {percentage:.0}%", self.msg("label-coverage")));
+                    if ui.button(self.msg("coverage-reset")).clicked() {
+                        self.tm_coverage.clear();
                     }
-                });
+                }
+                None => {
+                    ui.label(self.msg("coverage-none"));
+                }
+            }
+        });
+        ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
+            self.state_tools_ui(ui);
         });
+        if !is_tm_running && self.tm.is_some() {
+            ui.separator();
+            self.tape_editor_ui(ui);
+        }
         ui.separator();
         StripBuilder::new(ui)
             .size(Size::exact(260.0))
             .size(Size::exact(150.0))
             .size(Size::remainder())
             .horizontal(|mut strip| {
-                strip.cell(|ui| {
-                    ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
-                        ScrollArea::horizontal().show(ui, |ui| {
-                            self.table_command_ui(ui);
+                strip.cell(|ui| match self.tm_commands_view {
+                    CommandsView::Table => {
+                        ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
+                            ScrollArea::horizontal().show(ui, |ui| {
+                                self.table_command_ui(ui);
+                            });
                         });
-                    });
+                    }
+                    CommandsView::Expanded => self.expanded_commands_ui(ui),
+                    CommandsView::Matrix => {
+                        ui.add_enabled_ui(!is_tm_running && !is_tm_plotting, |ui| {
+                            self.matrix_command_ui(ui);
+                        });
+                    }
                 });
                 strip.cell(|ui| {
                     ScrollArea::horizontal().show(ui, |ui| {
@@ -494,23 +1093,354 @@ impl Application {
                     });
                 });
                 strip.cell(|ui| {
-                    let line = Line::new((*self.tm_plot_points.lock().unwrap()).to_owned())
-                        .name(self.msg("line-complexity"));
-                    let legend = Legend::default();
-                    Plot::new("plot")
-                        .x_axis_label(self.msg("axis-length-of-number"))
-                        .y_axis_label(self.msg("axis-max-steps"))
-                        .legend(legend)
-                        .y_axis_min_width(30.0)
-                        .show(ui, |ui| {
-                            ui.line(line);
-                        });
+                    ui.horizontal_wrapped(|ui| {
+                        for (view, label) in [
+                            (ChartView::Plot, "view-plot"),
+                            (ChartView::Trajectory, "view-trajectory"),
+                            (ChartView::Diagram, "view-diagram"),
+                            (ChartView::Statistics, "view-statistics"),
+                            (ChartView::Crossings, "view-crossings"),
+                            (ChartView::SpaceTime, "view-space-time"),
+                        ] {
+                            let label = self.msg(label);
+                            ui.selectable_value(&mut self.tm_chart_view, view, label);
+                        }
+                    });
+                    match self.tm_chart_view {
+                        ChartView::Plot => self.plot_ui(ui),
+                        ChartView::Trajectory => self.trajectory_ui(ui),
+                        ChartView::Diagram => self.diagram_ui(ui),
+                        ChartView::Statistics => self.statistics_ui(ui),
+                        ChartView::Crossings => self.crossings_ui(ui),
+                        ChartView::SpaceTime => self.space_time_ui(ui),
+                    }
                 });
             });
     }
 
+    fn plot_ui(&self, ui: &mut egui::Ui) {
+        let line = Line::new((*self.tm_plot_points.lock().unwrap()).to_owned())
+            .name(self.msg("line-complexity"));
+        let legend = Legend::default();
+        Plot::new("plot")
+            .x_axis_label(self.msg("axis-length-of-number"))
+            .y_axis_label(self.msg("axis-max-steps"))
+            .legend(legend)
+            .y_axis_min_width(30.0)
+            .show(ui, |ui| {
+                ui.line(line);
+            });
+    }
+
+    fn trajectory_ui(&mut self, ui: &mut egui::Ui) {
+        self.tm_trajectory.update(&self.tm_protocol.lock().unwrap());
+        let lines: Vec<_> = self
+            .tm_trajectory
+            .points
+            .iter()
+            .enumerate()
+            .map(|(n, points)| {
+                Line::new(points.to_owned()).name(format!("{} {}", self.msg("stats-tape"), n + 1))
+            })
+            .collect();
+        Plot::new("trajectory")
+            .x_axis_label(self.msg("axis-step"))
+            .y_axis_label(self.msg("axis-head-position"))
+            .legend(Legend::default())
+            .y_axis_min_width(30.0)
+            .show(ui, |ui| {
+                for line in lines {
+                    ui.line(line);
+                }
+            });
+    }
+
+    fn diagram_ui(&mut self, ui: &mut egui::Ui) {
+        let diagram = Diagram::new(&self.tm_commands, &self.tm_start_state, self.tm_blank);
+        let latest = self.tm_run_status.as_ref().map(|x| &x.latest);
+        let transition = latest
+            .and_then(|x| self.tm_commands.get(x.command?))
+            .map(|cmd| (cmd.istate.as_str(), cmd.ostate.as_str()));
+        let current = match &self.tm {
+            Some(tm) => Some(tm.current_state()),
+            None => latest.map(|x| x.state.as_str()),
+        };
+        diagram_ui(
+            ui,
+            &diagram,
+            current,
+            transition,
+            &mut self.tm_diagram_offset,
+        );
+    }
+
+    fn space_time_ui(&mut self, ui: &mut egui::Ui) {
+        let (rows, generation) = {
+            let protocol = self.tm_protocol.lock().unwrap();
+            (protocol.len().min(MAX_ROWS), protocol.generation())
+        };
+        if rows == 0 {
+            ui.label(self.msg("err-no-protocol"));
+            return;
+        }
+        let is_changed = self
+            .tm_space_time
+            .as_ref()
+            .is_none_or(|x| x.rows != rows || x.generation != generation);
+        if is_changed {
+            let space_time = SpaceTime::new((*self.tm_protocol.lock().unwrap()).iter());
+            let texture = ui.ctx().load_texture(
+                "space_time",
+                space_time.to_image(1),
+                egui::TextureOptions::NEAREST,
+            );
+            self.tm_space_time = Some(SpaceTimeTexture {
+                rows,
+                generation,
+                texture,
+                states: space_time.states,
+            });
+        }
+        let Some(space_time) = &self.tm_space_time else {
+            return;
+        };
+        ui.horizontal_wrapped(|ui| {
+            for (state, color) in &space_time.states {
+                ui.label(RichText::new("\u{25A0}").color(*color));
+                ui.label(state);
+            }
+        });
+        ScrollArea::both().show(ui, |ui| {
+            let texture = &space_time.texture;
+            ui.image((texture.id(), texture.size_vec2() * CELL_SIZE as f32));
+        });
+    }
+
+    fn statistics_ui(&self, ui: &mut egui::Ui) {
+        let Some(tm) = &self.tm else {
+            ui.label(self.msg("stats-none"));
+            return;
+        };
+        let stats = tm.stats();
+        let halt = match tm.halt_reason() {
+            None => self.msg("stats-stopped"),
+            Some(HaltReason::FinalState) => {
+                format!("{} {}", self.msg("stats-final-state"), tm.current_state())
+            }
+            Some(HaltReason::NoCommand(cells)) => {
+                let cells: Vec<_> = cells.iter().map(|x| x.name(self.tm_blank)).collect();
+                format!(
+                    "{} {} {}",
+                    self.msg("stats-no-command"),
+                    tm.current_state(),
+                    cells.join(" ")
+                )
+            }
+        };
+        ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("stats_summary").striped(true).show(ui, |ui| {
+                ui.label(self.msg("label-steps"));
+                ui.label(stats.steps.to_string());
+                ui.end_row();
+                ui.label(self.msg("stats-halt"));
+                ui.label(halt);
+                ui.end_row();
+            });
+            ui.separator();
+            Grid::new("stats_tapes").striped(true).show(ui, |ui| {
+                for label in ["stats-tape", "stats-extent", "stats-moves", "stats-visited"] {
+                    ui.strong(self.msg(label));
+                }
+                ui.end_row();
+                let visited = stats.cells_visited();
+                let tapes = stats.max_extents.iter().zip(&stats.head_moves).zip(visited);
+                for (n, ((extent, moves), visited)) in tapes.enumerate() {
+                    ui.label((n + 1).to_string());
+                    ui.label(extent.to_string());
+                    ui.label(moves.to_string());
+                    ui.label(visited.to_string());
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            let mut states: Vec<_> = stats.state_visits.iter().collect();
+            states.sort_by(|a, b| natural_cmp(a.0, b.0));
+            Grid::new("stats_states").striped(true).show(ui, |ui| {
+                ui.strong(self.msg("col-state"));
+                ui.strong(self.msg("stats-visits"));
+                ui.end_row();
+                for (state, visits) in states {
+                    ui.label(state);
+                    ui.label(visits.to_string());
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            Grid::new("stats_commands").striped(true).show(ui, |ui| {
+                ui.strong(self.msg("label-number-sign"));
+                ui.strong(self.msg("commands"));
+                ui.strong(self.msg("stats-fires"));
+                ui.end_row();
+                for (n, cmd) in self.tm_commands.iter().enumerate() {
+                    ui.label((n + 1).to_string());
+                    ui.label(format_command(cmd, self.tm_blank));
+                    ui.label(stats.command_fires.get(n).copied().unwrap_or(0).to_string());
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Also lists the longest sequences per length of the input in the plotting pass.
+    fn crossings_ui(&self, ui: &mut egui::Ui) {
+        /// Number of states shown in a sequence.
+        const MAX_SHOWN: usize = 100;
+        ScrollArea::vertical().show(ui, |ui| {
+            if self.num_tapes > 1 {
+                ui.label(self.msg("crossings-first-tape"));
+            }
+            match self.tm.as_ref().map(|tm| &tm.stats().crossings) {
+                None => {
+                    ui.label(self.msg("stats-none"));
+                }
+                Some(None) => {
+                    ui.label(self.msg("crossings-off"));
+                }
+                Some(Some(crossings)) => {
+                    Grid::new("crossings").striped(true).show(ui, |ui| {
+                        ui.strong(self.msg("crossings-boundary"));
+                        ui.strong(self.msg("crossings-length"));
+                        ui.strong(self.msg("crossings-sequence"));
+                        ui.end_row();
+                        for (boundary, states) in crossings.iter() {
+                            ui.label(boundary.to_string());
+                            ui.label(states.len().to_string());
+                            let mut sequence = states[..states.len().min(MAX_SHOWN)].join(" ");
+                            if states.len() > MAX_SHOWN {
+                                sequence += " \u{2026}";
+                            }
+                            ui.label(sequence);
+                            ui.end_row();
+                        }
+                    });
+                }
+            }
+            let plot_crossings = self.tm_plot_crossings.lock().unwrap();
+            if !plot_crossings.is_empty() {
+                ui.separator();
+                Grid::new("plot_crossings").striped(true).show(ui, |ui| {
+                    ui.strong(self.msg("axis-length-of-number"));
+                    ui.strong(self.msg("crossings-max-length"));
+                    ui.end_row();
+                    for (n, max_len) in plot_crossings.iter() {
+                        ui.label(n.to_string());
+                        ui.label(max_len.to_string());
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+    }
+
+    fn state_tools_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(self.msg("filter"));
+            let fields = [
+                (FilterField::All, self.msg("filter-all")),
+                (FilterField::State, self.msg("col-state")),
+                (FilterField::Read, self.msg("filter-read")),
+                (FilterField::Write, self.msg("filter-write")),
+                (FilterField::Direction, self.msg("col-dir")),
+            ];
+            let selected = fields
+                .iter()
+                .find(|(field, _)| *field == self.tm_filter_field)
+                .map(|(_, label)| label.to_owned())
+                .unwrap();
+            ComboBox::from_id_salt("filter_field")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    for (field, label) in fields {
+                        ui.selectable_value(&mut self.tm_filter_field, field, label);
+                    }
+                });
+            ui.add(egui::widgets::TextEdit::singleline(&mut self.tm_filter).desired_width(60.0));
+            ui.separator();
+            ui.label(self.msg("state-rename"));
+            ComboBox::from_id_salt("rename_from")
+                .selected_text(&self.tm_rename_from)
+                .show_ui(ui, |ui| {
+                    for state in self.states() {
+                        ui.selectable_value(&mut self.tm_rename_from, state.to_owned(), state);
+                    }
+                });
+            ui.label("\u{2192}");
+            ui.add(egui::widgets::TextEdit::singleline(&mut self.tm_rename_to).desired_width(40.0));
+            let states = self.states();
+            let is_valid = states.contains(&self.tm_rename_from)
+                && is_state_name_valid(&self.tm_rename_to)
+                && !self.tm_rename_to.contains(SEPARATOR)
+                && !states.contains(&self.tm_rename_to);
+            if ui
+                .add_enabled(is_valid, egui::Button::new(self.msg("btn-rename")))
+                .on_disabled_hover_text(self.msg("state-rename-hint"))
+                .clicked()
+            {
+                let names =
+                    HashMap::from([(self.tm_rename_from.to_owned(), self.tm_rename_to.to_owned())]);
+                self.rename_states(&names);
+                self.tm_rename_from = std::mem::take(&mut self.tm_rename_to);
+            }
+            ui.separator();
+            if ui.button(self.msg("states-renumber")).clicked() {
+                self.renumber_states();
+            }
+        });
+    }
+
+    fn rename_states(&mut self, names: &HashMap<String, String>) {
+        rename_states(&mut self.tm_commands, names);
+        if let Some(name) = names.get(&self.tm_start_state) {
+            self.tm_start_state = name.to_owned();
+        }
+        (*self.tm_protocol.lock().unwrap()).clear();
+        self.tm = None;
+    }
+
+    /// Renames the states to q0..qN in breadth-first order from the start state.
+    fn renumber_states(&mut self) {
+        let names = bfs_states(&self.tm_commands, &self.tm_start_state)
+            .into_iter()
+            .enumerate()
+            .map(|(n, state)| (state, format!("q{n}")))
+            .collect();
+        self.rename_states(&names);
+    }
+
+    fn is_command_visible(&self, cmd: &MultiCommand) -> bool {
+        let query = self.tm_filter.trim();
+        if query.is_empty() {
+            return true;
+        }
+        let field = self.tm_filter_field;
+        let is_any = field == FilterField::All;
+        ((is_any || field == FilterField::State)
+            && (cmd.istate.contains(query) || cmd.ostate.contains(query)))
+            || ((is_any || field == FilterField::Read)
+                && cmd.icells().iter().any(|c| c.name(self.tm_blank) == query))
+            || ((is_any || field == FilterField::Write)
+                && cmd.ocells().iter().any(|c| c.name(self.tm_blank) == query))
+            || ((is_any || field == FilterField::Direction)
+                && cmd.directions().iter().any(|d| d.to_string() == query))
+    }
+
     fn add_command(&mut self) {
-        self.tm_commands.push(
+        self.insert_command(self.tm_commands.len());
+    }
+
+    fn insert_command(&mut self, index: usize) {
+        self.tm_commands.insert(
+            index,
             MultiCommand::new(
                 "".into(),
                 vec![Cell::Blank; self.num_tapes],
@@ -520,20 +1450,84 @@ impl Application {
             )
             .unwrap(),
         );
+        self.select_command(index);
         (*self.tm_protocol.lock().unwrap()).clear();
     }
 
+    /// Removes the selected commands, or the last one if none is selected.
     fn remove_command(&mut self) {
-        self.tm_commands.pop();
+        if self.tm_selected_commands.is_empty() {
+            self.tm_commands.pop();
+        } else {
+            remove_rows(&mut self.tm_commands, &self.tm_selected_commands);
+        }
+        self.clear_selection();
         (*self.tm_protocol.lock().unwrap()).clear();
     }
 
+    fn duplicate_commands(&mut self) {
+        self.tm_selected_commands =
+            duplicate_rows(&mut self.tm_commands, &self.tm_selected_commands);
+        self.tm_selection_anchor = self.tm_selected_commands.first().copied();
+        (*self.tm_protocol.lock().unwrap()).clear();
+    }
+
+    /// Moves the selected commands onto `target`, or only `index` if it is not selected.
+    fn move_commands(&mut self, index: usize, target: usize) {
+        if !self.tm_selected_commands.contains(&index) {
+            self.select_command(index);
+        }
+        self.tm_selected_commands =
+            move_rows(&mut self.tm_commands, &self.tm_selected_commands, target);
+        self.tm_selection_anchor = self.tm_selected_commands.first().copied();
+        (*self.tm_protocol.lock().unwrap()).clear();
+    }
+
+    /// Sorts the commands by their input states; the order of commands of a state is kept.
+    fn sort_commands(&mut self) {
+        self.tm_commands
+            .sort_by(|a, b| natural_cmp(&a.istate, &b.istate));
+        self.clear_selection();
+        (*self.tm_protocol.lock().unwrap()).clear();
+    }
+
+    fn select_command(&mut self, index: usize) {
+        self.tm_selected_commands = BTreeSet::from([index]);
+        self.tm_selection_anchor = Some(index);
+    }
+
+    /// Selects like in file managers: a click selects one command, Ctrl toggles a command and
+    /// Shift selects a range.
+    fn click_command(&mut self, index: usize, modifiers: egui::Modifiers) {
+        match self.tm_selection_anchor {
+            Some(anchor) if modifiers.shift => {
+                self.tm_selected_commands = (anchor.min(index)..=anchor.max(index)).collect();
+            }
+            _ if modifiers.command => {
+                if !self.tm_selected_commands.remove(&index) {
+                    self.tm_selected_commands.insert(index);
+                }
+                self.tm_selection_anchor = Some(index);
+            }
+            _ if self.tm_selected_commands == BTreeSet::from([index]) => self.clear_selection(),
+            _ => self.select_command(index),
+        }
+    }
+
+    fn clear_selection(&mut self) {
+        self.tm_selected_commands.clear();
+        self.tm_selection_anchor = None;
+    }
+
     fn add_tape(&mut self) {
         for cmd in self.tm_commands.iter_mut() {
             cmd.add_tape();
         }
         self.num_tapes += 1;
+        self.tm_inputs.push("".into());
+        self.tm_heads.push(0);
         (*self.tm_protocol.lock().unwrap()).clear();
+        self.tm = None;
     }
 
     fn remove_tape(&mut self) {
@@ -544,33 +1538,181 @@ impl Application {
         for cmd in self.tm_commands.iter_mut() {
             cmd.remove_tape();
         }
+        self.tm_inputs.pop();
+        self.tm_heads.pop();
         (*self.tm_protocol.lock().unwrap()).clear();
+        self.tm = None;
+    }
+
+    fn compile_to_one_tape(&mut self) -> Result<()> {
+        let blank = self.tm_blank;
+        if self.tm_heads.iter().any(|&x| x != 0)
+            || self.tm_inputs[1..]
+                .iter()
+                .any(|x| x.chars().any(|c| c != blank))
+        {
+            return Err(anyhow!(self.msg("err-one-tape-start")));
+        }
+        let input: Vec<_> = self.tm_alphabet_primary.iter().map(|&x| x.into()).collect();
+        let single = SingleTape::new(
+            &self.tm_commands,
+            &self.tm_classes,
+            &input,
+            &self.tm_start_state,
+            self.tm_blank,
+        )?;
+        let mut program = self.to_program_file();
+        program.alphabet_secondary.extend(single.symbols());
+        program.num_tapes = 1;
+        program.start_state = single.start_state;
+        program.classes.clear();
+        program.commands = single.commands;
+        self.set_program_file(program);
+        Ok(())
+    }
+
+    /// The primary alphabet becomes `0` and `1`, so the plotting pass then runs on all binary
+    /// words. The plot of the old program is cleared.
+    fn compile_to_binary(&mut self) -> Result<()> {
+        let cells: Vec<_> = self
+            .tm_alphabet_primary
+            .iter()
+            .chain(self.tm_alphabet_secondary.iter())
+            .map(|&x| x.into())
+            .collect();
+        let binary = Binary::new(
+            &self.tm_commands,
+            &self.tm_classes,
+            &cells,
+            &self.tm_start_state,
+            self.tm_blank,
+        )?;
+        let inputs = self
+            .tm_inputs
+            .iter()
+            .map(|x| binary.encode(x))
+            .collect::<Result<Vec<_>>>()?;
+        let mut program = self.to_program_file();
+        program.alphabet_primary = Binary::symbols().to_vec();
+        program.alphabet_secondary.clear();
+        program.classes.clear();
+        program.commands = binary.commands;
+        self.set_program_file(program);
+        self.tm_inputs = inputs;
+        for head in self.tm_heads.iter_mut() {
+            *head *= binary.width as isize;
+        }
+        (*self.tm_plot_points.lock().unwrap()).clear();
+        (*self.tm_plot_crossings.lock().unwrap()).clear();
+        Ok(())
     }
 
-    fn start_tm(&mut self, ctx: &egui::Context) {
+    fn build_program(&self) -> Result<Program> {
+        build_program_with_classes(self.tm_commands.to_owned(), &self.tm_classes)
+    }
+
+    fn set_run_msg(&mut self, res: Result<()>) {
+        self.run_msg = match res {
+            Ok(_) => "".into(),
+            Err(e) => format!("{e}"),
+        };
+    }
+
+    fn start_tm(&mut self, ctx: &egui::Context) -> Result<()> {
         if self.tm_commands.is_empty() {
-            return;
+            return Ok(());
+        }
+        self.check_start_state()?;
+        let program = self.build_program()?;
+        let protocol = if self.tm_protocol_streamed {
+            let path = self.protocol_dialog()?;
+            Protocol::streaming(&path)
+                .context(self.msg("err-failed-to-create-open") + " " + path.to_str().unwrap())?
+        } else {
+            Protocol::default()
+        };
+        *self.tm_protocol.lock().unwrap() = protocol;
+        self.tm_run_status = None;
+        let start_tapes: Vec<_> = self.tm_inputs.iter().map(String::as_str).collect();
+        let start_state = self.tm_start_state.to_owned();
+        let mut tm = TuringMachine::with_blank(
+            &start_tapes,
+            &self.tm_heads,
+            start_state,
+            self.tm_blank,
+            program,
+        )?;
+        tm.record_crossings(self.tm_chart_view == ChartView::Crossings);
+        (*self.tm_protocol.lock().unwrap()).push_start(&tm);
+        self.run_tm(ctx, tm);
+        Ok(())
+    }
+
+    /// Continues the paused machine with the current commands.
+    fn continue_tm(&mut self, ctx: &egui::Context) -> Result<()> {
+        let program = self.build_program()?;
+        let Some(mut tm) = self.tm.take() else {
+            return Ok(());
+        };
+        let res = tm.set_program(program);
+        if res.is_ok() {
+            (*self.tm_protocol.lock().unwrap()).checkpoint(&tm);
+            self.run_tm(ctx, tm);
+        } else {
+            self.tm = Some(tm);
         }
+        res
+    }
+
+    fn run_tm(&mut self, ctx: &egui::Context, mut tm: TuringMachine) {
         self.is_tm_running.store(true, Ordering::Relaxed);
-        (*self.tm_protocol.lock().unwrap()).clear();
-        let mut start_tapes = vec![""; self.num_tapes];
-        start_tapes[0] = &self.tm_input;
-        let tm = TuringMachine::from_multi(&start_tapes, self.tm_commands.to_owned()).unwrap();
+        self.tm_edit_cell = None;
+        self.tm_run_commands = self.tm_commands.to_owned();
         let tm_protocol = Arc::clone(&self.tm_protocol);
         let is_tm_running = Arc::clone(&self.is_tm_running);
         let is_tm_stop_requested = Arc::clone(&self.is_tm_stop_requested);
+        let (sender, receiver) = mpsc::channel();
+        self.tm_updates = Some(receiver);
         let ctx = ctx.clone();
         self.tm_thread = Some(thread::spawn(move || {
-            (*tm_protocol.lock().unwrap()).push(tm.to_strings());
-            for strings in tm {
-                (*tm_protocol.lock().unwrap()).push(strings);
-                ctx.request_repaint();
-                if is_tm_stop_requested.load(Ordering::Relaxed) {
+            let start = Instant::now();
+            let mut last_update = start;
+            let mut batch = (*tm_protocol.lock().unwrap()).batch();
+            let mut steps: usize = 0;
+            loop {
+                let is_halted = !tm.step();
+                if !is_halted {
+                    batch.push_step(&tm);
+                    steps += 1;
+                }
+                let is_stopped = is_halted || is_tm_stop_requested.load(Ordering::Relaxed);
+                // Checking the time on every step would slow the machine down.
+                if !is_stopped
+                    && (!steps.is_multiple_of(1024) || last_update.elapsed() < RunUpdate::PERIOD)
+                {
+                    continue;
+                }
+                last_update = Instant::now();
+                if let Some(latest) = batch.last().cloned() {
+                    _ = sender.send(RunUpdate {
+                        steps,
+                        latest,
+                        elapsed: start.elapsed(),
+                    });
+                }
+                let mut protocol = tm_protocol.lock().unwrap();
+                protocol.append(&mut batch);
+                if is_stopped {
+                    protocol.flush();
                     break;
                 }
+                drop(protocol);
+                ctx.request_repaint();
             }
             is_tm_stop_requested.store(false, Ordering::Relaxed);
             is_tm_running.store(false, Ordering::Relaxed);
+            ctx.request_repaint();
+            tm
         }));
     }
 
@@ -578,31 +1720,51 @@ impl Application {
         self.is_tm_stop_requested.store(true, Ordering::Relaxed);
     }
 
-    fn start_plot(&mut self, ctx: &egui::Context) {
+    fn start_plot(&mut self, ctx: &egui::Context) -> Result<()> {
+        if self.tm_commands.is_empty() {
+            return Ok(());
+        }
+        self.check_start_state()?;
+        let program = self.build_program()?;
         self.is_tm_plotting.store(true, Ordering::Relaxed);
         (*self.tm_plot_points.lock().unwrap()).clear();
-        let tm_commands = self.tm_commands.to_owned();
-        let alphabet: Vec<char> = self.tm_alphabet_primary.chars().collect();
+        (*self.tm_plot_crossings.lock().unwrap()).clear();
+        self.tm_plot_commands = self.tm_commands.to_owned();
+        let alphabet = self.tm_alphabet_primary.to_owned();
         let is_tm_plotting = Arc::clone(&self.is_tm_plotting);
         let is_tm_stop_plot_requested = Arc::clone(&self.is_tm_stop_plot_requested);
         let tm_plot_points = Arc::clone(&self.tm_plot_points);
-        let start_state = tm_commands[0].istate.to_owned();
+        let tm_plot_crossings = Arc::clone(&self.tm_plot_crossings);
+        let start_state = self.tm_start_state.to_owned();
         let num_tapes = self.num_tapes;
+        let tm_inputs = self.tm_inputs.to_owned();
+        let tm_heads = self.tm_heads.to_owned();
+        let blank = self.tm_blank;
         let ctx = ctx.clone();
         self.tm_plot_thread = Some(thread::spawn(move || {
             let enough = || is_tm_stop_plot_requested.load(Ordering::Relaxed);
-            let mut tm =
-                TuringMachine::from_multi(&vec![""; num_tapes], tm_commands.to_owned()).unwrap();
+            let mut tm = TuringMachine::with_blank(
+                &vec![""; num_tapes],
+                &vec![0; num_tapes],
+                start_state.to_owned(),
+                blank,
+                program,
+            )
+            .unwrap();
+            tm.record_crossings(true);
+            let mut fires = Vec::new();
             'outer: for n in 1.. {
                 let mut max_steps = 0;
+                let mut max_crossings = 0;
                 for input in alphabet.get_exhaustive_words(n) {
-                    let mut steps = 0;
-                    let mut start_tapes = vec![""; num_tapes];
+                    let mut steps: usize = 0;
+                    let mut start_tapes: Vec<_> = tm_inputs.iter().map(String::as_str).collect();
                     start_tapes[0] = &input;
-                    tm.restart(&start_tapes, start_state.to_owned()).unwrap();
+                    tm.restart(&start_tapes, &tm_heads, start_state.to_owned())
+                        .unwrap();
                     'out: loop {
                         for _ in 0..500 {
-                            if tm.next().is_none() {
+                            if !tm.step() {
                                 break 'out;
                             }
                             steps += 1;
@@ -611,12 +1773,16 @@ impl Application {
                             break 'outer;
                         }
                     }
+                    add_fires(&mut fires, &tm.stats().command_fires);
+                    let crossings = tm.stats().crossings.as_ref();
+                    max_crossings = max_crossings.max(crossings.map_or(0, Crossings::max_len));
                     if enough() {
                         break 'outer;
                     }
                     max_steps = max_steps.max(steps);
                 }
                 (*tm_plot_points.lock().unwrap()).push([n as f64, max_steps as f64]);
+                (*tm_plot_crossings.lock().unwrap()).push((n, max_crossings));
                 ctx.request_repaint();
                 if enough() {
                     break;
@@ -624,7 +1790,9 @@ impl Application {
             }
             is_tm_stop_plot_requested.store(false, Ordering::Relaxed);
             is_tm_plotting.store(false, Ordering::Relaxed);
+            fires
         }));
+        Ok(())
     }
 
     fn request_stop_plot(&mut self) {
@@ -633,32 +1801,458 @@ impl Application {
     }
 
     fn save_protocol(&self) -> Result<()> {
+        if (*self.tm_protocol.lock().unwrap()).is_empty() {
+            return Err(anyhow!(self.msg("err-no-protocol")));
+        }
+        let path = self.protocol_dialog()?;
+        let file = File::create(&path)
+            .context(self.msg("err-failed-to-create-open") + " " + path.to_str().unwrap())?;
+        let mut writer = BufWriter::new(file);
+        write_protocol(
+            &mut writer,
+            (*self.tm_protocol.lock().unwrap()).iter(),
+            ProtocolFormat::from_path(&path),
+        )
+        .and_then(|_| writer.flush())
+        .context(self.msg("err-failed-to-write") + " " + path.to_str().unwrap())
+    }
+
+    fn protocol_dialog(&self) -> Result<PathBuf> {
+        let mut dialog = rfd::FileDialog::new().set_file_name("protocol.txt");
+        for format in ProtocolFormat::ALL {
+            dialog = dialog.add_filter(format.name(), &[format.extension()]);
+        }
+        dialog
+            .save_file()
+            .ok_or_else(|| anyhow!(self.msg("err-no-path-given")))
+    }
+
+    fn export_dot(&self) -> Result<()> {
+        let diagram = Diagram::new(&self.tm_commands, &self.tm_start_state, self.tm_blank);
+        self.export_program("program.dot", diagram.to_dot())
+    }
+
+    fn export_latex_table(&self) -> Result<()> {
+        let header = [
+            self.msg("col-state"),
+            self.msg("col-cell"),
+            self.msg("col-dir"),
+        ];
+        let tex = commands_tabular(
+            &self.tm_commands,
+            self.tm_blank,
+            header.each_ref().map(String::as_str),
+        );
+        self.export_program("program.tex", tex)
+    }
+
+    fn export_tikz(&self) -> Result<()> {
+        let diagram = Diagram::new(&self.tm_commands, &self.tm_start_state, self.tm_blank);
+        self.export_program("diagram.tex", diagram_tikz(&diagram, self.tm_blank))
+    }
+
+    fn export_latex_protocol(&self) -> Result<()> {
         if (*self.tm_protocol.lock().unwrap()).is_empty() {
             return Err(anyhow!(self.msg("err-no-protocol")));
         }
         let path = rfd::FileDialog::new()
-            .set_file_name("protocol.txt")
-            .save_file();
+            .set_file_name("protocol.tex")
+            .save_file()
+            .ok_or_else(|| anyhow!(self.msg("err-no-path-given")))?;
+        let file = File::create(&path)
+            .context(self.msg("err-failed-to-create-open") + " " + path.to_str().unwrap())?;
+        // The rows are written as the protocol is replayed, so it is never held whole.
+        let mut writer = BufWriter::new(file);
+        protocol_enumerate(&mut writer, (*self.tm_protocol.lock().unwrap()).iter())
+            .and_then(|_| writer.flush())
+            .context(self.msg("err-failed-to-write") + " " + path.to_str().unwrap())
+    }
+
+    fn export_space_time(&self) -> Result<()> {
+        let space_time = {
+            let protocol = self.tm_protocol.lock().unwrap();
+            if protocol.is_empty() {
+                return Err(anyhow!(self.msg("err-no-protocol")));
+            }
+            SpaceTime::new(protocol.iter())
+        };
+        let path = rfd::FileDialog::new()
+            .set_file_name("space_time.png")
+            .add_filter("PNG", &["png"])
+            .save_file()
+            .ok_or_else(|| anyhow!(self.msg("err-no-path-given")))?;
+        space_time
+            .write_png(&path, CELL_SIZE)
+            .context(self.msg("err-failed-to-write") + " " + path.to_str().unwrap())
+    }
+
+    fn export_program(&self, file_name: &str, text: String) -> Result<()> {
+        if self.tm_commands.is_empty() {
+            return Err(anyhow!(self.msg("err-no-commands")));
+        }
+        self.export_text(file_name, text)
+    }
+
+    fn export_text(&self, file_name: &str, text: String) -> Result<()> {
+        let path = rfd::FileDialog::new().set_file_name(file_name).save_file();
         let path = match path {
             Some(p) => p,
             None => return Err(anyhow!(self.msg("err-no-path-given"))),
         };
         let mut file = File::create(&path)
             .context(self.msg("err-failed-to-create-open") + " " + path.to_str().unwrap())?;
-        let mut protocol = String::new();
-        for s in &*self.tm_protocol.lock().unwrap() {
-            for t in s {
-                protocol.push_str(t);
-                protocol.push(' ');
+        file.write_all(text.as_bytes())
+            .context(self.msg("err-failed-to-write") + " " + path.to_str().unwrap())?;
+        Ok(())
+    }
+
+    fn tape_editor_ui(&mut self, ui: &mut egui::Ui) {
+        let states = self.states();
+        let symbols: Vec<_> = self
+            .tm_alphabet_primary
+            .iter()
+            .chain(self.tm_alphabet_secondary.iter())
+            .copied()
+            .collect();
+        let blank = self.tm_blank;
+        let label_current_state = self.msg("current-state");
+        let label_symbol = self.msg("symbol");
+        let label_move_head = self.msg("btn-move-head");
+        let Some(tm) = &mut self.tm else {
+            return;
+        };
+        let mut is_edited = false;
+        ui.horizontal(|ui| {
+            ui.label(label_current_state);
+            let mut state = tm.current_state().to_owned();
+            ComboBox::from_id_salt("current_state")
+                .selected_text(&state)
+                .show_ui(ui, |ui| {
+                    for s in states {
+                        ui.selectable_value(&mut state, s.to_owned(), s);
+                    }
+                });
+            if state != tm.current_state() {
+                tm.set_current_state(state);
+                is_edited = true;
             }
-            _ = protocol.pop();
-            protocol.push('\n');
+        });
+        let mut edit_cell = self.tm_edit_cell;
+        ScrollArea::horizontal()
+            .id_salt("tape_editor")
+            .show(ui, |ui| {
+                for (n, tape) in tm.tapes().iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}", n + 1));
+                        // Leave a couple of blank cells on each side to extend the tape.
+                        let range = tape.range();
+                        for position in range.start() - 2..=range.end() + 2 {
+                            let mut text =
+                                RichText::new(tape.get_at(position).name(blank)).monospace();
+                            if position == tape.head() {
+                                text = text.strong().underline();
+                            }
+                            let is_selected = edit_cell == Some((n, position));
+                            if ui.selectable_label(is_selected, text).clicked() {
+                                edit_cell = if is_selected {
+                                    None
+                                } else {
+                                    Some((n, position))
+                                };
+                            }
+                        }
+                    });
+                }
+            });
+        if let Some((n, position)) = edit_cell {
+            ui.horizontal(|ui| {
+                ui.label(label_symbol);
+                let mut cell = *tm.tapes()[n].get_at(position);
+                let old_cell = cell;
+                ComboBox::from_id_salt("edit_cell")
+                    .selected_text(cell.name(blank))
+                    .width(Self::COMBO_BOX_CELL_WIDTH)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut cell, Cell::Blank, blank.to_string());
+                        for &symbol in &symbols {
+                            ui.selectable_value(&mut cell, Cell::Symbol(symbol), symbol.name());
+                        }
+                    });
+                if cell != old_cell {
+                    tm.set_cell(n, position, cell).unwrap();
+                    is_edited = true;
+                }
+                if ui.button(label_move_head).clicked() {
+                    tm.set_head(n, position).unwrap();
+                    is_edited = true;
+                }
+            });
+        }
+        self.tm_edit_cell = edit_cell;
+        if is_edited {
+            (*self.tm_protocol.lock().unwrap()).push_edit(tm);
+        }
+    }
+
+    fn to_program_file(&self) -> ProgramFile {
+        ProgramFile {
+            blank: self.tm_blank,
+            alphabet_primary: self.tm_alphabet_primary.to_owned(),
+            alphabet_secondary: self.tm_alphabet_secondary.to_owned(),
+            num_tapes: self.num_tapes,
+            start_state: self.tm_start_state.to_owned(),
+            classes: self.tm_classes.to_owned(),
+            commands: self.tm_commands.to_owned(),
         }
-        file.write(protocol.as_bytes())
+    }
+
+    fn save_program(&mut self) -> Result<()> {
+        let program = self.to_program_file();
+        program.validate()?;
+        let path = rfd::FileDialog::new()
+            .add_filter("Turing Machine", &["tm"])
+            .set_file_name("program.tm")
+            .save_file();
+        let path = match path {
+            Some(p) => p,
+            None => return Err(anyhow!(self.msg("err-no-path-given"))),
+        };
+        let mut file = File::create(&path)
+            .context(self.msg("err-failed-to-create-open") + " " + path.to_str().unwrap())?;
+        file.write_all(program.to_string().as_bytes())
             .context(self.msg("err-failed-to-write") + " " + path.to_str().unwrap())?;
+        self.saved_program = program;
+        Ok(())
+    }
+
+    fn load_program(&mut self) -> Result<()> {
+        let path = rfd::FileDialog::new()
+            .add_filter("Turing Machine", &["tm"])
+            .pick_file();
+        let path = match path {
+            Some(p) => p,
+            None => return Err(anyhow!(self.msg("err-no-path-given"))),
+        };
+        let s = fs::read_to_string(&path)
+            .context(self.msg("err-failed-to-read") + " " + path.to_str().unwrap())?;
+        let program = ProgramFile::parse(&s)?;
+        (*self.tm_protocol.lock().unwrap()).clear();
+        self.tm = None;
+        self.saved_program = program.clone();
+        self.set_program_file(program);
+        self.tm_inputs = vec!["".into(); self.num_tapes];
+        self.tm_heads = vec![0; self.num_tapes];
+        Ok(())
+    }
+
+    fn set_program_file(&mut self, program: ProgramFile) {
+        if program.num_tapes != self.num_tapes || program.blank != self.tm_blank {
+            (*self.tm_protocol.lock().unwrap()).clear();
+            self.tm = None;
+        }
+        self.convert_inputs(program.blank);
+        self.tm_blank = program.blank;
+        self.tm_alphabet_primary = program.alphabet_primary;
+        self.tm_alphabet_secondary = program.alphabet_secondary;
+        self.num_tapes = program.num_tapes;
+        self.tm_start_state = program.start_state;
+        self.tm_classes = program.classes;
+        self.tm_commands = program.commands;
+        self.tm_inputs.resize(self.num_tapes, "".into());
+        self.tm_heads.resize(self.num_tapes, 0);
+        self.clear_selection();
+    }
+
+    fn is_program_modified(&self) -> bool {
+        self.to_program_file() != self.saved_program
+    }
+
+    /// Records the program once a text field is left, so that typing makes one undo step.
+    fn record_history(&mut self, is_editing_text: bool, has_input: bool) {
+        let was_editing_text = std::mem::replace(&mut self.is_editing_text, is_editing_text);
+        if !is_editing_text && (was_editing_text || has_input) {
+            self.history.record(self.to_program_file());
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(program) = self.history.undo() {
+            self.set_program_file(program);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(program) = self.history.redo() {
+            self.set_program_file(program);
+        }
+    }
+
+    fn undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let redo = egui::KeyboardShortcut::new(
+            egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+            egui::Key::Z,
+        );
+        let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        if ctx.input_mut(|i| i.consume_shortcut(&redo)) {
+            self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&undo)) {
+            self.undo();
+        }
+    }
+
+    fn clipboard_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let events = ctx.input(|i| i.events.to_owned());
+        for event in events {
+            match event {
+                egui::Event::Copy if !self.tm_selected_commands.is_empty() => {
+                    ctx.copy_text(self.selected_commands_text());
+                }
+                egui::Event::Paste(text) => {
+                    if let Err(e) = self.paste_commands(&text) {
+                        self.paste_msg = format!("{e:#}");
+                        self.paste_text = Some(text);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn selected_commands_text(&self) -> String {
+        self.tm_selected_commands
+            .iter()
+            .map(|&n| format_command(&self.tm_commands[n], self.tm_blank) + "\n")
+            .collect()
+    }
+
+    /// Inserts the commands of `text` after the selected ones, or at the end.
+    fn paste_commands(&mut self, text: &str) -> Result<()> {
+        let mut commands = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cmd = parse_command(line, self.num_tapes, self.tm_blank)
+                .and_then(|cmd| self.check_command(&cmd).map(|_| cmd))
+                .with_context(|| format!("{} {}", self.msg("line"), n + 1))?;
+            commands.push(cmd);
+        }
+        if commands.is_empty() {
+            return Err(anyhow!(self.msg("err-no-commands")));
+        }
+        let index = match self.tm_selected_commands.last() {
+            Some(&last) => last + 1,
+            None => self.tm_commands.len(),
+        };
+        let length = commands.len();
+        self.tm_commands.splice(index..index, commands);
+        self.tm_selected_commands = (index..index + length).collect();
+        self.tm_selection_anchor = Some(index);
+        (*self.tm_protocol.lock().unwrap()).clear();
+        Ok(())
+    }
+
+    fn check_command(&self, cmd: &MultiCommand) -> Result<()> {
+        let is_known = |cell: &Cell| match cell {
+            Cell::Blank => true,
+            Cell::Symbol(symbol) => {
+                self.tm_alphabet_primary.contains(symbol)
+                    || self.tm_alphabet_secondary.contains(symbol)
+            }
+        };
+        let is_class_known = |name: &String| self.tm_classes.iter().any(|c| &c.name == name);
+        for icell in cmd.icells() {
+            let is_valid = match icell {
+                CellPattern::Cell(cell) => is_known(cell),
+                CellPattern::Class(name) => is_class_known(name),
+                CellPattern::Any | CellPattern::NonBlank => true,
+            };
+            if !is_valid {
+                return Err(anyhow!(
+                    "{}: {}",
+                    self.msg("err-unknown-symbol"),
+                    icell.name(self.tm_blank)
+                ));
+            }
+        }
+        for ocell in cmd.ocells() {
+            let is_valid = match ocell {
+                CellWrite::Cell(cell) => is_known(cell),
+                CellWrite::Class(name) => is_class_known(name),
+                CellWrite::Same => true,
+            };
+            if !is_valid {
+                return Err(anyhow!(
+                    "{}: {}",
+                    self.msg("err-unknown-symbol"),
+                    ocell.name(self.tm_blank)
+                ));
+            }
+        }
         Ok(())
     }
 
+    fn paste_ui(&mut self, ctx: &egui::Context) {
+        let Some(mut text) = self.paste_text.take() else {
+            return;
+        };
+        let mut is_open = true;
+        let mut is_done = false;
+        egui::Window::new(self.msg("commands-paste-title"))
+            .open(&mut is_open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(self.msg("commands-paste-hint"));
+                ui.add(
+                    egui::widgets::TextEdit::multiline(&mut text)
+                        .code_editor()
+                        .desired_rows(8),
+                );
+                if !self.paste_msg.is_empty() {
+                    ui.colored_label(ui.visuals().error_fg_color, &self.paste_msg);
+                }
+                if ui.button(self.msg("btn-insert")).clicked() {
+                    match self.paste_commands(&text) {
+                        Ok(_) => is_done = true,
+                        Err(e) => self.paste_msg = format!("{e:#}"),
+                    }
+                }
+            });
+        if is_open && !is_done {
+            self.paste_text = Some(text);
+        }
+    }
+
+    fn confirm_preset_ui(&mut self, ctx: &egui::Context) {
+        let Some(preset) = self.tm_pending_preset.clone() else {
+            return;
+        };
+        egui::Window::new(self.msg("confirm-preset-title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.msg("confirm-preset"));
+                ui.horizontal(|ui| {
+                    if ui.button(self.msg("btn-replace")).clicked() {
+                        self.tm_preset = preset;
+                        self.tm_pending_preset = None;
+                        self.set_preset();
+                    }
+                    if ui.button(self.msg("btn-cancel")).clicked() {
+                        self.tm_pending_preset = None;
+                    }
+                });
+            });
+    }
+
     fn table_command_ui(&mut self, ui: &mut egui::Ui) {
         let text_style_height = ui.text_style_height(&egui::TextStyle::Button);
         let item_spacing_height = ui.spacing().item_spacing.y;
@@ -671,10 +2265,13 @@ impl Application {
         TableBuilder::new(ui)
             .striped(true)
             .cell_layout(Layout::left_to_right(Align::Center))
-            .columns(Column::auto(), 5)
+            .columns(Column::auto(), 6)
             .min_scrolled_height(0.0)
             .max_scroll_height(available_height)
             .header(20.0, |mut header| {
+                header.col(|ui| {
+                    ui.strong(self.msg("label-number-sign"));
+                });
                 header.col(|ui| {
                     ui.strong(self.msg("col-state"));
                 });
@@ -692,9 +2289,64 @@ impl Application {
                 });
             })
             .body(|body| {
-                body.rows(text_height, self.tm_commands.len(), |mut row| {
-                    let index = row.index();
+                let mut clicked = None;
+                let mut dropped = None;
+                let mut inserted = None;
+                let mut is_duplicated = false;
+                let mut is_removed = false;
+                let visible: Vec<_> = (0..self.tm_commands.len())
+                    .filter(|&n| self.is_command_visible(&self.tm_commands[n]))
+                    .collect();
+                body.rows(text_height, visible.len(), |mut row| {
+                    let index = visible[row.index()];
+                    row.set_selected(self.tm_selected_commands.contains(&index));
+                    row.col(|ui| {
+                        let id = egui::Id::new(("command_row", index));
+                        let response = ui
+                            .dnd_drag_source(id, index, |ui| {
+                                let mut text = RichText::new(format!("{} \u{2261}", index + 1));
+                                match self.tm_coverage.is_fired(&self.tm_commands, index) {
+                                    Some(true) => text = text.color(Color32::DARK_GREEN),
+                                    Some(false) => text = text.color(Color32::RED),
+                                    None => {}
+                                }
+                                ui.add(egui::Label::new(text).sense(egui::Sense::click()))
+                            })
+                            .inner;
+                        if response.clicked() {
+                            clicked = Some((index, ui.input(|i| i.modifiers)));
+                        }
+                        response.context_menu(|ui| {
+                            if !self.tm_selected_commands.contains(&index) {
+                                self.select_command(index);
+                            }
+                            if ui.button(self.msg("command-insert-above")).clicked() {
+                                inserted = Some(index);
+                                ui.close_menu();
+                            }
+                            if ui.button(self.msg("command-insert-below")).clicked() {
+                                inserted = Some(index + 1);
+                                ui.close_menu();
+                            }
+                            if ui.button(self.msg("command-duplicate")).clicked() {
+                                is_duplicated = true;
+                                ui.close_menu();
+                            }
+                            if ui.button(self.msg("commands-copy")).clicked() {
+                                ui.ctx().copy_text(self.selected_commands_text());
+                                ui.close_menu();
+                            }
+                            if ui.button(self.msg("command-delete")).clicked() {
+                                is_removed = true;
+                                ui.close_menu();
+                            }
+                        });
+                    });
                     let col_state = self.msg("col-state");
+                    let label_any = self.msg("pattern-any");
+                    let label_non_blank = self.msg("pattern-non-blank");
+                    let label_same = self.msg("pattern-same");
+                    let label_class_write = self.msg("pattern-class-write");
                     row.col(|ui| {
                         ui.add(
                             egui::widgets::TextEdit::singleline(
@@ -709,25 +2361,43 @@ impl Application {
                             for i in 0..self.num_tapes {
                                 let icell = self.tm_commands[index].get_mut_icell(i).unwrap();
                                 ComboBox::from_id_salt(format!("icell{i}"))
-                                    .selected_text(char::from(*icell).to_string())
+                                    .selected_text(icell.name(self.tm_blank))
                                     .width(Self::COMBO_BOX_CELL_WIDTH)
                                     .show_ui(ui, |ui| {
                                         ui.selectable_value(
                                             icell,
-                                            Cell::Blank,
-                                            BLANK_CHAR.to_string(),
+                                            Cell::Blank.into(),
+                                            self.tm_blank.to_string(),
                                         );
-                                        for ch in self
+                                        for &symbol in self
                                             .tm_alphabet_primary
-                                            .chars()
-                                            .chain(self.tm_alphabet_secondary.chars())
+                                            .iter()
+                                            .chain(self.tm_alphabet_secondary.iter())
                                         {
                                             ui.selectable_value(
                                                 icell,
-                                                Cell::Symbol(ch),
-                                                ch.to_string(),
+                                                Cell::Symbol(symbol).into(),
+                                                symbol.name(),
                                             );
                                         }
+                                        ui.separator();
+                                        ui.selectable_value(
+                                            icell,
+                                            CellPattern::Any,
+                                            CellPattern::ANY,
+                                        )
+                                        .on_hover_text(&label_any);
+                                        ui.selectable_value(
+                                            icell,
+                                            CellPattern::NonBlank,
+                                            CellPattern::NON_BLANK,
+                                        )
+                                        .on_hover_text(&label_non_blank);
+                                        for class in &self.tm_classes {
+                                            let pattern = CellPattern::Class(class.name.to_owned());
+                                            let text = pattern.name(self.tm_blank);
+                                            ui.selectable_value(icell, pattern, text);
+                                        }
                                     });
                             }
                         });
@@ -746,25 +2416,38 @@ impl Application {
                             for i in 0..self.num_tapes {
                                 let ocell = self.tm_commands[index].get_mut_ocell(i).unwrap();
                                 ComboBox::from_id_salt(format!("ocell{i}"))
-                                    .selected_text(char::from(*ocell).to_string())
+                                    .selected_text(ocell.name(self.tm_blank))
                                     .width(Self::COMBO_BOX_CELL_WIDTH)
                                     .show_ui(ui, |ui| {
                                         ui.selectable_value(
                                             ocell,
-                                            Cell::Blank,
-                                            BLANK_CHAR.to_string(),
+                                            Cell::Blank.into(),
+                                            self.tm_blank.to_string(),
                                         );
-                                        for ch in self
+                                        for &symbol in self
                                             .tm_alphabet_primary
-                                            .chars()
-                                            .chain(self.tm_alphabet_secondary.chars())
+                                            .iter()
+                                            .chain(self.tm_alphabet_secondary.iter())
                                         {
                                             ui.selectable_value(
                                                 ocell,
-                                                Cell::Symbol(ch),
-                                                ch.to_string(),
+                                                Cell::Symbol(symbol).into(),
+                                                symbol.name(),
                                             );
                                         }
+                                        ui.separator();
+                                        ui.selectable_value(
+                                            ocell,
+                                            CellWrite::Same,
+                                            CellWrite::SAME,
+                                        )
+                                        .on_hover_text(&label_same);
+                                        for class in &self.tm_classes {
+                                            let write = CellWrite::Class(class.name.to_owned());
+                                            let text = write.name(self.tm_blank);
+                                            ui.selectable_value(ocell, write, text)
+                                                .on_hover_text(&label_class_write);
+                                        }
                                     });
                             }
                         });
@@ -774,37 +2457,211 @@ impl Application {
                             for i in 0..self.num_tapes {
                                 let direction =
                                     self.tm_commands[index].get_mut_direction(i).unwrap();
-                                ComboBox::from_id_salt(format!("direction{i}"))
-                                    .selected_text(direction.to_string())
-                                    .width(Self::COMBO_BOX_CELL_WIDTH)
-                                    .show_ui(ui, |ui| {
-                                        ui.selectable_value(
-                                            direction,
-                                            Direction::Left,
-                                            Direction::Left.to_string(),
-                                        );
-                                        ui.selectable_value(
-                                            direction,
-                                            Direction::None,
-                                            Direction::None.to_string(),
-                                        );
-                                        ui.selectable_value(
-                                            direction,
-                                            Direction::Right,
-                                            Direction::Right.to_string(),
-                                        );
-                                    });
+                                Self::direction_combo_ui(ui, format!("direction{i}"), direction);
                             }
                         });
                     });
+                    if let Some(from) = row.response().dnd_release_payload::<usize>() {
+                        dropped = Some((*from, index));
+                    }
+                });
+                if let Some((index, modifiers)) = clicked {
+                    self.click_command(index, modifiers);
+                }
+                if let Some((index, target)) = dropped {
+                    self.move_commands(index, target);
+                }
+                if let Some(index) = inserted {
+                    self.insert_command(index);
+                }
+                if is_duplicated {
+                    self.duplicate_commands();
+                }
+                if is_removed {
+                    self.remove_command();
+                }
+            });
+    }
+
+    fn expanded_commands_ui(&self, ui: &mut egui::Ui) {
+        match expand_commands(&self.tm_commands, &self.cells(), &self.tm_classes) {
+            Ok(commands) => {
+                ui.strong(format!("{}: {}", self.msg("commands"), commands.len()));
+                ScrollArea::both()
+                    .id_salt("expanded_commands")
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for cmd in &commands {
+                            ui.monospace(format_command(cmd, self.tm_blank));
+                        }
+                    });
+            }
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{e}"));
+            }
+        }
+    }
+
+    /// Cells covered only by templates are weak, and uncovered cells are gaps.
+    fn matrix_command_ui(&mut self, ui: &mut egui::Ui) {
+        let expanded = match expand_commands(&self.tm_commands, &self.cells(), &self.tm_classes) {
+            Ok(commands) => commands,
+            Err(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{e}"));
+                return;
+            }
+        };
+        let blank = self.tm_blank;
+        let states = self.states();
+        let columns = product(&vec![self.cells(); self.num_tapes]);
+        let mut exact = HashMap::new();
+        for (n, cmd) in self.tm_commands.iter().enumerate() {
+            if let Some(icells) = cmd.exact_icells() {
+                exact.insert((cmd.istate.as_str(), icells), n);
+            }
+        }
+        let effective: HashMap<_, _> = expanded
+            .iter()
+            .filter_map(|cmd| Some(((cmd.istate.as_str(), cmd.exact_icells()?), cmd)))
+            .collect();
+        let names = |cells: &[Cell]| {
+            cells
+                .iter()
+                .map(|cell| cell.name(blank))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let transition = |cmd: &MultiCommand| {
+            let ocells: Vec<_> = cmd.ocells().iter().map(|c| c.name(blank)).collect();
+            let directions: Vec<_> = cmd.directions().iter().map(|d| d.to_string()).collect();
+            format!(
+                "{} {} {}",
+                cmd.ostate,
+                ocells.join(","),
+                directions.join(",")
+            )
+        };
+        let label_template = self.msg("matrix-template");
+        let mut selected = self.tm_matrix_cell.take();
+        ScrollArea::both()
+            .id_salt("matrix")
+            .max_height(ui.available_height() - 80.0)
+            .show(ui, |ui| {
+                Grid::new("grid_matrix").striped(true).show(ui, |ui| {
+                    ui.label("");
+                    for icells in &columns {
+                        ui.strong(names(icells));
+                    }
+                    ui.end_row();
+                    for state in &states {
+                        ui.strong(state);
+                        for icells in &columns {
+                            let key = (state.as_str(), icells.to_owned());
+                            let text = if let Some(&n) = exact.get(&key) {
+                                RichText::new(transition(&self.tm_commands[n]))
+                            } else if let Some(cmd) = effective.get(&key) {
+                                RichText::new(transition(cmd)).weak().italics()
+                            } else {
+                                RichText::new("\u{00B7}").weak()
+                            };
+                            let is_selected = selected
+                                .as_ref()
+                                .is_some_and(|(s, c)| s == state && c == icells);
+                            let mut response = ui.selectable_label(is_selected, text);
+                            if !exact.contains_key(&key) && effective.contains_key(&key) {
+                                response = response.on_hover_text(&label_template);
+                            }
+                            if response.clicked() {
+                                selected = if is_selected {
+                                    None
+                                } else {
+                                    Some((state.to_owned(), icells.to_owned()))
+                                };
+                            }
+                        }
+                        ui.end_row();
+                    }
                 });
             });
+        let Some((state, icells)) = selected else {
+            return;
+        };
+        ui.separator();
+        let index = exact.get(&(state.as_str(), icells.to_owned())).copied();
+        let template = effective
+            .get(&(state.as_str(), icells.to_owned()))
+            .map(|&cmd| cmd.to_owned());
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(format!("{state} {}", names(&icells)));
+            let Some(n) = index else {
+                if ui.button(self.msg("matrix-add")).clicked() {
+                    let cmd = template.unwrap_or_else(|| {
+                        MultiCommand::new(
+                            state.to_owned(),
+                            icells.to_owned(),
+                            state.to_owned(),
+                            icells.to_owned(),
+                            vec![Direction::None; self.num_tapes],
+                        )
+                        .unwrap()
+                    });
+                    self.tm_commands.push(cmd);
+                    (*self.tm_protocol.lock().unwrap()).clear();
+                }
+                return;
+            };
+            ui.label("\u{2192}");
+            ui.add(
+                egui::widgets::TextEdit::singleline(&mut self.tm_commands[n].ostate)
+                    .desired_width(40.0),
+            );
+            for i in 0..self.num_tapes {
+                let ocell = self.tm_commands[n].get_mut_ocell(i).unwrap();
+                ComboBox::from_id_salt(format!("matrix_ocell{i}"))
+                    .selected_text(ocell.name(blank))
+                    .width(Self::COMBO_BOX_CELL_WIDTH)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(ocell, Cell::Blank.into(), blank.to_string());
+                        for &symbol in self
+                            .tm_alphabet_primary
+                            .iter()
+                            .chain(self.tm_alphabet_secondary.iter())
+                        {
+                            ui.selectable_value(ocell, Cell::Symbol(symbol).into(), symbol.name());
+                        }
+                        ui.separator();
+                        ui.selectable_value(ocell, CellWrite::Same, CellWrite::SAME);
+                    });
+            }
+            for i in 0..self.num_tapes {
+                let direction = self.tm_commands[n].get_mut_direction(i).unwrap();
+                Self::direction_combo_ui(ui, format!("matrix_direction{i}"), direction);
+            }
+            if ui.button(self.msg("matrix-delete")).clicked() {
+                self.tm_commands.remove(n);
+                self.clear_selection();
+                (*self.tm_protocol.lock().unwrap()).clear();
+            }
+        });
+        self.tm_matrix_cell = Some((state, icells));
+    }
+
+    fn direction_combo_ui(ui: &mut egui::Ui, id_salt: String, direction: &mut Direction) {
+        ComboBox::from_id_salt(id_salt)
+            .selected_text(direction.to_string())
+            .width(Self::COMBO_BOX_CELL_WIDTH)
+            .show_ui(ui, |ui| {
+                for d in [Direction::Left, Direction::None, Direction::Right] {
+                    ui.selectable_value(direction, d, d.to_string());
+                }
+            });
     }
 
     fn table_protocol_ui(&mut self, ui: &mut egui::Ui) {
         let text_height = ui.text_style_height(&egui::TextStyle::Body) * self.num_tapes as f32;
         let available_height = ui.available_height();
         let length = (*self.tm_protocol.lock().unwrap()).len();
+        let mut window = std::mem::take(&mut self.tm_protocol_window);
         TableBuilder::new(ui)
             .striped(true)
             .cell_layout(Layout::left_to_right(Align::Center))
@@ -845,14 +2702,25 @@ impl Application {
                     } else {
                         row.index()
                     };
+                    let protocol = self.tm_protocol.lock().unwrap();
+                    let Some(entry) = window.get(&protocol, index).cloned() else {
+                        return;
+                    };
+                    drop(protocol);
                     row.col(|ui| {
-                        ui.label(index.to_string());
+                        if entry.is_edit {
+                            ui.label(format!("{index} {}", ProtocolEntry::EDIT_MARK))
+                                .on_hover_text(self.msg("label-manual-edit"));
+                        } else {
+                            ui.label(index.to_string());
+                        }
                     });
                     row.col(|ui| {
-                        ui.label((*self.tm_protocol.lock().unwrap())[index].join("\n"));
+                        ui.label(entry.strings().join("\n"));
                     });
                 });
             });
+        self.tm_protocol_window = window;
     }
 
     fn zoom(&mut self, ctx: &egui::Context, inc: f32) {
@@ -866,12 +2734,18 @@ impl Application {
     fn join_threads(&mut self) {
         if !self.is_tm_running.load(Ordering::Relaxed) {
             if let Some(jh) = self.tm_thread.take() {
-                _ = jh.join();
+                self.tm = jh.join().ok();
+                if let Some(tm) = &self.tm {
+                    self.tm_coverage
+                        .record(&self.tm_run_commands, &tm.stats().command_fires);
+                }
             }
         }
         if !self.is_tm_plotting.load(Ordering::Relaxed) {
             if let Some(jh) = self.tm_plot_thread.take() {
-                _ = jh.join();
+                if let Ok(fires) = jh.join() {
+                    self.tm_coverage.record(&self.tm_plot_commands, &fires);
+                }
             }
         }
     }
@@ -879,8 +2753,28 @@ impl Application {
 
 impl eframe::App for Application {
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        if let Some(updates) = &self.tm_updates {
+            if let Some(update) = updates.try_iter().last() {
+                self.tm_run_status = Some(update);
+            }
+        }
         self.join_threads();
+        if let Some(e) = (*self.tm_protocol.lock().unwrap()).take_stream_error() {
+            self.run_msg = self.msg("err-failed-to-write") + " " + &e;
+        }
+        let is_editable = !self.is_tm_running.load(Ordering::Relaxed)
+            && !self.is_tm_plotting.load(Ordering::Relaxed);
+        if is_editable && self.tm_pending_preset.is_none() && self.paste_text.is_none() {
+            self.undo_shortcuts(ctx);
+            self.clipboard_shortcuts(ctx);
+        }
         egui::CentralPanel::default().show(ctx, |ui| self.main_ui(ui));
+        self.confirm_preset_ui(ctx);
+        if is_editable {
+            self.paste_ui(ctx);
+        }
+        let has_input = ctx.input(|i| !i.events.is_empty());
+        self.record_history(ctx.wants_keyboard_input(), has_input);
         if ctx.input(|i| i.viewport().close_requested()) {
             if self.is_tm_running.load(Ordering::Relaxed) {
                 self.request_stop_tm();
@@ -1130,4 +3024,107 @@ mod tests {
             TuringMachine::from_multi(&["aaabbbccc", ""], Application::preset_multitape()).unwrap();
         assert_eq!(tm.last().unwrap()[0], "qz0");
     }
+
+    #[test]
+    fn test_paste_commands_1() {
+        let mut app = Application::new(1.0);
+        let length = app.tm_commands.len();
+        app.tm_selected_commands = BTreeSet::from([0, 1]);
+        let text = app.selected_commands_text();
+        app.tm_selected_commands.clear();
+        app.paste_commands(&format!("# Copied.\n{text}\n")).unwrap();
+        assert_eq!(app.tm_commands.len(), length + 2);
+        assert_eq!(app.tm_commands[length..], app.tm_commands[..2]);
+        assert_eq!(
+            app.tm_selected_commands,
+            BTreeSet::from([length, length + 1])
+        );
+    }
+
+    #[test]
+    fn test_paste_commands_2() {
+        let mut app = Application::new(1.0);
+        let length = app.tm_commands.len();
+        assert!(app
+            .paste_commands("q0 a -> q1 b R\nq1 x -> q1 b R\n")
+            .is_err());
+        assert!(app.paste_commands("q0 a a -> q1 b b R R\n").is_err());
+        assert!(app.paste_commands("q0 {v} -> q1 {=} R\n").is_err());
+        assert!(app.paste_commands("\n").is_err());
+        assert_eq!(app.tm_commands.len(), length);
+    }
+
+    #[test]
+    fn test_check_start_state() {
+        let mut app = Application::new(1.0);
+        assert!(app.check_start_state().is_ok());
+        app.tm_start_state = "missing".into();
+        assert!(app.check_start_state().is_err());
+        assert_eq!(app.tm_start_state, "q0");
+        app.tm_commands.clear();
+        app.tm_start_state = "missing".into();
+        assert!(app.check_start_state().is_ok());
+    }
+
+    #[test]
+    fn test_compile_to_binary() {
+        let mut app = Application::new(1.0);
+        app.tm_inputs = vec!["ab".into()];
+        app.tm_heads = vec![1];
+        (*app.tm_plot_points.lock().unwrap()).push([1.0, 1.0]);
+        app.compile_to_binary().unwrap();
+        assert_eq!(app.tm_alphabet_primary, Binary::symbols());
+        assert!(app.tm_alphabet_secondary.is_empty());
+        // Eight symbols take blocks of three cells.
+        assert_eq!(app.tm_inputs, ["000001"]);
+        assert_eq!(app.tm_heads, [3]);
+        assert!(app.tm_plot_points.lock().unwrap().is_empty());
+        let commands = app.tm_commands.to_owned();
+        app.tm_blank = '0';
+        assert!(app.compile_to_binary().is_err());
+        assert_eq!(app.tm_commands, commands);
+    }
+
+    #[test]
+    fn test_compile_to_one_tape() {
+        let mut app = Application::new(1.0);
+        app.add_tape();
+        app.tm_inputs[1] = "a".into();
+        let commands = app.tm_commands.to_owned();
+        assert!(app.compile_to_one_tape().is_err());
+        assert_eq!(app.tm_commands, commands);
+        app.tm_inputs[1] = BLANK_CHAR.into();
+        app.tm_heads[0] = 1;
+        assert!(app.compile_to_one_tape().is_err());
+        app.tm_heads[0] = 0;
+        app.compile_to_one_tape().unwrap();
+        assert_eq!(app.num_tapes, 1);
+    }
+
+    #[test]
+    fn test_record_history() {
+        let mut app = Application::new(1.0);
+        for ch in ['q', '9'] {
+            app.tm_commands[0].ostate.push(ch);
+            app.record_history(true, true);
+        }
+        assert!(!app.history.can_undo());
+        app.record_history(false, false);
+        let ostate = app.tm_commands[0].ostate.to_owned();
+        app.undo();
+        assert_eq!(app.tm_commands[0].ostate, ostate[..ostate.len() - 2]);
+        assert!(!app.history.can_undo());
+    }
+
+    #[test]
+    fn test_renumber_states() {
+        let mut app = Application::new(1.0);
+        let states = bfs_states(&app.tm_commands, "q0");
+        let halting = states.iter().position(|s| s == "qz").unwrap();
+        app.renumber_states();
+        assert_eq!(app.tm_start_state, "q0");
+        assert_eq!(app.states().len(), states.len());
+        let tm = TuringMachine::from_multi(&["aabcc"], app.tm_commands).unwrap();
+        assert_eq!(tm.last().unwrap(), [format!("q{halting}1")]);
+    }
 }
@@ -0,0 +1,415 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Plain text format of a program:
+//!
+//! ```text
+//! # Comment.
+//! blank: λ
+//! alphabet-primary: a b c
+//! alphabet-secondary: 0 1 X1 X2
+//! tapes: 2
+//! start: q0
+//! class: ab a b
+//!
+//! q0 a λ -> q1 a X1 R R
+//! q1 {ab} {*} -> q1 {=} {=} R N
+//! ```
+
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::turing_machine::cell::{Cell, BLANK_CHAR};
+use crate::turing_machine::core::{CellPattern, CellWrite, Direction, MultiCommand, SymbolClass};
+use crate::turing_machine::symbol::Symbol;
+
+const ARROW: &str = "->";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramFile {
+    pub blank: char,
+    pub alphabet_primary: Vec<Symbol>,
+    pub alphabet_secondary: Vec<Symbol>,
+    pub num_tapes: usize,
+    pub start_state: String,
+    pub classes: Vec<SymbolClass>,
+    pub commands: Vec<MultiCommand>,
+}
+
+impl ProgramFile {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut blank = BLANK_CHAR;
+        let mut alphabet_primary = Vec::new();
+        let mut alphabet_secondary = Vec::new();
+        let mut num_tapes = None;
+        let mut start_state = None;
+        let mut classes = Vec::new();
+        let mut commands = Vec::new();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = || format!("line {}", n + 1);
+            // States cannot contain colons, so only a key can end with one.
+            let key_value = line
+                .split_once(char::is_whitespace)
+                .map_or((line, ""), |(key, value)| (key, value.trim()));
+            if let (Some(key), value) = (key_value.0.strip_suffix(':'), key_value.1) {
+                match key {
+                    "blank" => {
+                        let mut chars = value.chars();
+                        blank = match (chars.next(), chars.next()) {
+                            (Some(ch), None) => ch,
+                            _ => return Err(anyhow!("invalid blank")).with_context(context),
+                        };
+                    }
+                    "alphabet-primary" => {
+                        alphabet_primary = parse_alphabet(value).with_context(context)?
+                    }
+                    "alphabet-secondary" => {
+                        alphabet_secondary = parse_alphabet(value).with_context(context)?
+                    }
+                    "tapes" => {
+                        num_tapes = Some(
+                            value
+                                .parse::<usize>()
+                                .ok()
+                                .filter(|&t| t > 0)
+                                .ok_or_else(|| anyhow!("invalid number of tapes"))
+                                .with_context(context)?,
+                        )
+                    }
+                    "start" => start_state = Some(value.to_owned()),
+                    "class" => classes.push(parse_class(value, blank).with_context(context)?),
+                    _ => return Err(anyhow!("unknown key")).with_context(context),
+                }
+                continue;
+            }
+            let num_tapes = num_tapes
+                .ok_or_else(|| anyhow!("number of tapes is not given"))
+                .with_context(context)?;
+            commands.push(parse_command(line, num_tapes, blank).with_context(context)?);
+        }
+        let num_tapes = num_tapes.ok_or_else(|| anyhow!("number of tapes is not given"))?;
+        let start_state = match start_state {
+            Some(s) => s,
+            None => match commands.first() {
+                Some(cmd) => cmd.istate.to_owned(),
+                None => return Err(anyhow!("no commands")),
+            },
+        };
+        Ok(Self {
+            blank,
+            alphabet_primary,
+            alphabet_secondary,
+            num_tapes,
+            start_state,
+            classes,
+            commands,
+        })
+    }
+
+    /// Checks that the program can be written and read back unchanged.
+    pub fn validate(&self) -> Result<()> {
+        if self.blank.is_whitespace() {
+            return Err(anyhow!("invalid blank"));
+        }
+        if !is_state_name_valid(&self.start_state) {
+            return Err(anyhow!("invalid state: {:?}", self.start_state));
+        }
+        for (n, class) in self.classes.iter().enumerate() {
+            if !SymbolClass::is_name_valid(&class.name)
+                || self.classes[..n].iter().any(|c| c.name == class.name)
+            {
+                return Err(anyhow!("invalid class: {:?}", class.name));
+            }
+        }
+        for (n, cmd) in self.commands.iter().enumerate() {
+            for state in [&cmd.istate, &cmd.ostate] {
+                if !is_state_name_valid(state) {
+                    return Err(anyhow!("invalid state: {state:?}"))
+                        .with_context(|| format!("command {}", n + 1));
+                }
+            }
+            if cmd.len() != self.num_tapes {
+                return Err(anyhow!("invalid tapes length"))
+                    .with_context(|| format!("command {}", n + 1));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that a state name can be written to a program file and read back.
+pub fn is_state_name_valid(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('#')
+        && !name.contains(ARROW)
+        && !name.contains(char::is_whitespace)
+        && !name.contains(':')
+}
+
+fn parse_alphabet(s: &str) -> Result<Vec<Symbol>> {
+    s.split_whitespace().map(Symbol::parse).collect()
+}
+
+fn format_alphabet(alphabet: &[Symbol]) -> String {
+    alphabet
+        .iter()
+        .map(|symbol| symbol.name())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_class(s: &str, blank: char) -> Result<SymbolClass> {
+    let mut names = s.split_whitespace();
+    let name = names.next().unwrap_or_default();
+    if !SymbolClass::is_name_valid(name) {
+        return Err(anyhow!("invalid class: {name:?}"));
+    }
+    Ok(SymbolClass {
+        name: name.to_owned(),
+        cells: names
+            .map(|name| Cell::parse(name, blank))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn format_class(class: &SymbolClass, blank: char) -> String {
+    let mut s = class.name.to_owned();
+    for cell in &class.cells {
+        s.push(' ');
+        s.push_str(&cell.name(blank));
+    }
+    s
+}
+
+pub fn parse_command(line: &str, num_tapes: usize, blank: char) -> Result<MultiCommand> {
+    let (input, output) = line
+        .split_once(ARROW)
+        .ok_or_else(|| anyhow!("expected {ARROW}"))?;
+    let input: Vec<_> = input.split_whitespace().collect();
+    let output: Vec<_> = output.split_whitespace().collect();
+    if input.len() != 1 + num_tapes || output.len() != 1 + 2 * num_tapes {
+        return Err(anyhow!("invalid number of fields"));
+    }
+    let parse_direction = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Direction::from_char(ch),
+            _ => Err(anyhow!("invalid direction: {s:?}")),
+        }
+    };
+    MultiCommand::with_patterns(
+        input[0].into(),
+        input[1..]
+            .iter()
+            .map(|s| CellPattern::parse(s, blank))
+            .collect::<Result<_>>()?,
+        output[0].into(),
+        output[1..=num_tapes]
+            .iter()
+            .map(|s| CellWrite::parse(s, blank))
+            .collect::<Result<_>>()?,
+        output[1 + num_tapes..]
+            .iter()
+            .map(|s| parse_direction(s))
+            .collect::<Result<_>>()?,
+    )
+}
+
+pub fn format_command(cmd: &MultiCommand, blank: char) -> String {
+    let mut s = cmd.istate.to_owned();
+    for cell in cmd.icells() {
+        s.push(' ');
+        s.push_str(&cell.name(blank));
+    }
+    s.push(' ');
+    s.push_str(ARROW);
+    s.push(' ');
+    s.push_str(&cmd.ostate);
+    for cell in cmd.ocells() {
+        s.push(' ');
+        s.push_str(&cell.name(blank));
+    }
+    for direction in cmd.directions() {
+        s.push(' ');
+        s.push_str(&direction.to_string());
+    }
+    s
+}
+
+impl fmt::Display for ProgramFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "blank: {}", self.blank)?;
+        writeln!(
+            f,
+            "alphabet-primary: {}",
+            format_alphabet(&self.alphabet_primary)
+        )?;
+        writeln!(
+            f,
+            "alphabet-secondary: {}",
+            format_alphabet(&self.alphabet_secondary)
+        )?;
+        writeln!(f, "tapes: {}", self.num_tapes)?;
+        writeln!(f, "start: {}", self.start_state)?;
+        for class in &self.classes {
+            writeln!(f, "class: {}", format_class(class, self.blank))?;
+        }
+        writeln!(f)?;
+        for cmd in &self.commands {
+            writeln!(f, "{}", format_command(cmd, self.blank))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_mcmd;
+
+    fn program() -> ProgramFile {
+        ProgramFile {
+            blank: '_',
+            alphabet_primary: vec!['a'.into(), 'b'.into()],
+            alphabet_secondary: vec!["X1".into()],
+            num_tapes: 2,
+            start_state: "q0".into(),
+            classes: vec![SymbolClass {
+                name: "ab".into(),
+                cells: vec!['a'.into(), Cell::Blank],
+            }],
+            commands: vec![
+                tm_mcmd!("q0", ['a', Cell::Blank], "q1", ['a', "X1"], ['R', 'R']),
+                tm_mcmd!(
+                    "q1",
+                    [Cell::Blank, "X1"],
+                    "qz",
+                    ['b', Cell::Blank],
+                    ['N', 'L']
+                ),
+                MultiCommand::with_patterns(
+                    "qz".into(),
+                    vec![CellPattern::Class("ab".into()), CellPattern::Any],
+                    "qz".into(),
+                    vec![CellWrite::Same, 'b'.into()],
+                    vec![Direction::Right, Direction::None],
+                )
+                .unwrap(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            program().to_string(),
+            "blank: _\n\
+             alphabet-primary: a b\n\
+             alphabet-secondary: X1\n\
+             tapes: 2\n\
+             start: q0\n\
+             class: ab a _\n\
+             \n\
+             q0 a _ -> q1 a X1 R R\n\
+             q1 _ X1 -> qz b _ N L\n\
+             qz {ab} {*} -> qz {=} b R N\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_1() {
+        let program = program();
+        assert_eq!(ProgramFile::parse(&program.to_string()).unwrap(), program);
+    }
+
+    #[test]
+    fn test_parse_2() {
+        let program = ProgramFile::parse(
+            "# Comment.\n\
+             tapes: 1\n\
+             q0 λ -> qz 0 N\n",
+        )
+        .unwrap();
+        assert_eq!(program.blank, BLANK_CHAR);
+        assert_eq!(program.start_state, "q0");
+        assert_eq!(
+            program.commands,
+            vec![tm_mcmd!("q0", [Cell::Blank], "qz", ['0'], ['N'])]
+        );
+    }
+
+    #[test]
+    fn test_parse_3() {
+        assert!(ProgramFile::parse("q0 a -> q0 a R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 a q0 a R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 a -> q0 a R R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 a{ -> q0 a R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nalphabet-primary: a}\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 a -> q0 a X\n").is_err());
+        assert!(ProgramFile::parse("tapes: 0\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nblank: ab\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nfoo: bar\n").is_err());
+        assert!(ProgramFile::parse("tapes:1\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 {=} -> q0 a R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nq0 a -> q0 {*} R\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nclass: {a} a\n").is_err());
+        assert!(ProgramFile::parse("tapes: 1\nclass: * a\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_4() {
+        let program = ProgramFile::parse(
+            "tapes: 1\n\
+             alphabet-primary:\n\
+             alphabet-secondary: : #\n\
+             q0 : -> q0 # R\n",
+        )
+        .unwrap();
+        assert_eq!(program.alphabet_primary, vec![]);
+        assert_eq!(program.alphabet_secondary, vec![':'.into(), '#'.into()]);
+        assert_eq!(
+            program.commands,
+            vec![tm_mcmd!("q0", [':'], "q0", ['#'], ['R'])]
+        );
+    }
+
+    #[test]
+    fn test_parse_5() {
+        let program = ProgramFile::parse(
+            "tapes: 1\n\
+             class: l a b\n\
+             class: u A B\n\
+             q0 {l} -> q0 {u} R\n",
+        )
+        .unwrap();
+        assert_eq!(program.commands[0].ocells(), [CellWrite::Class("u".into())]);
+        assert_eq!(ProgramFile::parse(&program.to_string()).unwrap(), program);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut program = program();
+        assert!(program.validate().is_ok());
+        program.commands[0].ostate = "".into();
+        assert!(program.validate().is_err());
+        program.commands[0].ostate = "q 1".into();
+        assert!(program.validate().is_err());
+        program.commands[0].ostate = "#q1".into();
+        assert!(program.validate().is_err());
+        program.commands[0].ostate = "q:1".into();
+        assert!(program.validate().is_err());
+        program.commands[0].ostate = "q->1".into();
+        assert!(program.validate().is_err());
+        program.commands[0].ostate = "q1#".into();
+        assert!(program.validate().is_ok());
+        program = self::program();
+        program.classes.push(program.classes[0].clone());
+        assert!(program.validate().is_err());
+    }
+}
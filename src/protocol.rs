@@ -0,0 +1,909 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+
+use crate::turing_machine::tape::TapeSnapshot;
+use crate::turing_machine::TuringMachine;
+
+#[derive(Clone, PartialEq)]
+pub struct ProtocolEntry {
+    pub state: String,
+    pub tapes: Vec<TapeSnapshot>,
+    /// Index of the command that led to the entry.
+    pub command: Option<usize>,
+    pub is_edit: bool,
+}
+
+impl ProtocolEntry {
+    pub const EDIT_MARK: char = '\u{270E}'; // Pencil.
+
+    pub fn step(tm: &TuringMachine) -> Self {
+        Self {
+            state: tm.current_state().to_owned(),
+            tapes: tm.snapshots(),
+            command: tm.last_command(),
+            is_edit: false,
+        }
+    }
+
+    pub fn start(tm: &TuringMachine) -> Self {
+        Self {
+            command: None,
+            ..Self::step(tm)
+        }
+    }
+
+    pub fn edit(tm: &TuringMachine) -> Self {
+        Self {
+            command: None,
+            is_edit: true,
+            ..Self::step(tm)
+        }
+    }
+
+    pub fn strings(&self) -> Vec<String> {
+        self.tapes
+            .iter()
+            .map(|tape| tape.to_string_with_state(&self.state))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProtocolFormat {
+    Text,
+    Csv,
+    Json,
+    Html,
+}
+
+impl ProtocolFormat {
+    pub const ALL: [Self; 4] = [Self::Text, Self::Csv, Self::Json, Self::Html];
+
+    /// Chooses the format by the extension of `path`, plain text by default.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("csv") => Self::Csv,
+            Some("json") => Self::Json,
+            Some("html" | "htm") => Self::Html,
+            _ => Self::Text,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Text => "Text",
+            Self::Csv => "CSV",
+            Self::Json => "JSON",
+            Self::Html => "HTML",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Writes the protocol entry by entry, where commands are numbered from one as in the table of
+/// commands.
+pub fn write_protocol(
+    out: &mut impl Write,
+    entries: impl IntoIterator<Item = ProtocolEntry>,
+    format: ProtocolFormat,
+) -> std::io::Result<()> {
+    for (step, entry) in entries.into_iter().enumerate() {
+        if step == 0 {
+            out.write_all(format_header(format, entry.tapes.len()).as_bytes())?;
+        }
+        out.write_all(format_entry(format, step, &entry).as_bytes())?;
+    }
+    out.write_all(format_footer(format).as_bytes())
+}
+
+fn format_header(format: ProtocolFormat, num_tapes: usize) -> String {
+    match format {
+        ProtocolFormat::Text => "".into(),
+        ProtocolFormat::Csv => {
+            let mut header = vec![
+                "step".to_owned(),
+                "state".into(),
+                "command".into(),
+                "edit".into(),
+            ];
+            for n in 1..=num_tapes {
+                header.push(format!("head{n}"));
+                header.push(format!("tape{n}"));
+            }
+            header.join(",") + "\n"
+        }
+        ProtocolFormat::Json => "[\n".into(),
+        ProtocolFormat::Html => "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Protocol</title>
+<style>
+table { border-collapse: collapse; font-family: monospace; }
+td, th { border: 1px solid #999; padding: 2px 6px; vertical-align: top; }
+.head { background: #fd6; font-weight: bold; }
+</style>
+</head>
+<body>
+<table>
+<tr><th>#</th><th>State</th><th>Command</th><th>Tapes</th></tr>
+"
+        .into(),
+    }
+}
+
+fn format_entry(format: ProtocolFormat, step: usize, entry: &ProtocolEntry) -> String {
+    match format {
+        ProtocolFormat::Text => text_entry(entry),
+        ProtocolFormat::Csv => csv_entry(step, entry),
+        ProtocolFormat::Json => json_entry(step, entry),
+        ProtocolFormat::Html => html_entry(step, entry),
+    }
+}
+
+fn format_footer(format: ProtocolFormat) -> String {
+    match format {
+        ProtocolFormat::Text | ProtocolFormat::Csv => "".into(),
+        ProtocolFormat::Json => "\n]\n".into(),
+        ProtocolFormat::Html => "</table>\n</body>\n</html>\n".into(),
+    }
+}
+
+fn text_entry(entry: &ProtocolEntry) -> String {
+    let mut line = String::new();
+    if entry.is_edit {
+        line.push(ProtocolEntry::EDIT_MARK);
+        line.push(' ');
+    }
+    line.push_str(&entry.strings().join(" "));
+    line.push('\n');
+    line
+}
+
+fn command_number(entry: &ProtocolEntry) -> Option<String> {
+    entry.command.map(|n| (n + 1).to_string())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn csv_entry(step: usize, entry: &ProtocolEntry) -> String {
+    let mut row = vec![
+        step.to_string(),
+        csv_field(&entry.state),
+        command_number(entry).unwrap_or_default(),
+        entry.is_edit.to_string(),
+    ];
+    for tape in &entry.tapes {
+        row.push(tape.position.to_string());
+        row.push(csv_field(&tape.to_string()));
+    }
+    row.join(",") + "\n"
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if ch.is_control() => json.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_entry(step: usize, entry: &ProtocolEntry) -> String {
+    let heads: Vec<_> = entry.tapes.iter().map(|x| x.position.to_string()).collect();
+    let tapes: Vec<_> = entry
+        .tapes
+        .iter()
+        .map(|x| json_string(&x.to_string()))
+        .collect();
+    format!(
+        "{}  {{\"step\": {step}, \"state\": {}, \"command\": {}, \"edit\": {}, \"heads\": [{}], \"tapes\": [{}]}}",
+        if step == 0 { "" } else { ",\n" },
+        json_string(&entry.state),
+        command_number(entry).unwrap_or_else(|| "null".into()),
+        entry.is_edit,
+        heads.join(", "),
+        tapes.join(", "),
+    )
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html_entry(step: usize, entry: &ProtocolEntry) -> String {
+    let mark = if entry.is_edit {
+        format!(" {}", ProtocolEntry::EDIT_MARK)
+    } else {
+        "".into()
+    };
+    let tapes: Vec<_> = entry
+        .tapes
+        .iter()
+        .map(|tape| {
+            let mut cells = String::new();
+            for (n, cell) in tape.cells.iter().enumerate() {
+                let cell = html_escape(&cell.to_string_with_blank(tape.blank));
+                if n == tape.head {
+                    cells += &format!("<span class=\"head\">{cell}</span>");
+                } else {
+                    cells += &cell;
+                }
+            }
+            cells
+        })
+        .collect();
+    format!(
+        "<tr><td>{step}{mark}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        html_escape(&entry.state),
+        command_number(entry).unwrap_or_default(),
+        tapes.join("<br>"),
+    )
+}
+
+struct Stream {
+    writer: BufWriter<File>,
+    format: ProtocolFormat,
+    is_started: bool,
+}
+
+impl Stream {
+    fn write(&mut self, step: usize, entry: &ProtocolEntry) -> std::io::Result<()> {
+        if !self.is_started {
+            let header = format_header(self.format, entry.tapes.len());
+            self.writer.write_all(header.as_bytes())?;
+            self.is_started = true;
+        }
+        let entry = format_entry(self.format, step, entry);
+        self.writer.write_all(entry.as_bytes())
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if self.is_started {
+            _ = self.writer.write_all(format_footer(self.format).as_bytes());
+        }
+        _ = self.writer.flush();
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bounds {
+    /// Number of the recent entries.
+    recent: usize,
+    /// Number of entries between checkpoints.
+    interval: usize,
+}
+
+impl Bounds {
+    const RECENT_LIMIT: usize = 10_000;
+    const CHECKPOINT_INTERVAL: usize = 10_000;
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            recent: Self::RECENT_LIMIT,
+            interval: Self::CHECKPOINT_INTERVAL,
+        }
+    }
+}
+
+struct Checkpoint {
+    index: usize,
+    entry: ProtocolEntry,
+    tm: TuringMachine,
+}
+
+/// Steps of a run collected apart from a [`Protocol`], e.g. so that it is locked once per batch.
+pub struct ProtocolBatch {
+    entries: Vec<ProtocolEntry>,
+    checkpoints: Vec<Checkpoint>,
+    /// Index of the next entry in the protocol.
+    index: usize,
+    interval: Option<usize>,
+}
+
+impl ProtocolBatch {
+    pub fn push_step(&mut self, tm: &TuringMachine) {
+        let entry = ProtocolEntry::step(tm);
+        if self.interval.is_some_and(|x| self.index.is_multiple_of(x)) {
+            self.checkpoints.push(Checkpoint {
+                index: self.index,
+                entry: entry.clone(),
+                tm: tm.without_stats(),
+            });
+        }
+        self.entries.push(entry);
+        self.index += 1;
+    }
+
+    pub fn last(&self) -> Option<&ProtocolEntry> {
+        self.entries.last()
+    }
+}
+
+/// A streamed protocol keeps only the recent entries in memory and replays the older ones from
+/// the nearest checkpoint.
+pub struct Protocol {
+    recent: VecDeque<ProtocolEntry>,
+    len: usize,
+    bounds: Option<Bounds>,
+    checkpoints: Vec<Checkpoint>,
+    stream: Option<Stream>,
+    stream_error: Option<String>,
+    generation: usize,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self {
+            recent: VecDeque::new(),
+            len: 0,
+            bounds: None,
+            checkpoints: Vec::new(),
+            stream: None,
+            stream_error: None,
+            generation: next_generation(),
+        }
+    }
+}
+
+fn next_generation() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Protocol {
+    pub fn streaming(path: &Path) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self {
+            bounds: Some(Bounds::default()),
+            stream: Some(Stream {
+                writer: BufWriter::new(file),
+                format: ProtocolFormat::from_path(path),
+                is_started: false,
+            }),
+            ..Self::default()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Changes whenever the protocol is replaced, cleared or edited.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn push_start(&mut self, tm: &TuringMachine) {
+        self.push(ProtocolEntry::start(tm), tm, true);
+    }
+
+    #[cfg(test)]
+    pub fn push_step(&mut self, tm: &TuringMachine) {
+        let is_checkpoint = self.is_checkpoint_due(self.len);
+        self.push(ProtocolEntry::step(tm), tm, is_checkpoint);
+    }
+
+    #[cfg(test)]
+    fn is_checkpoint_due(&self, index: usize) -> bool {
+        self.bounds
+            .is_some_and(|x| index.is_multiple_of(x.interval))
+    }
+
+    pub fn batch(&self) -> ProtocolBatch {
+        ProtocolBatch {
+            entries: Vec::new(),
+            checkpoints: Vec::new(),
+            index: self.len,
+            interval: self.bounds.map(|x| x.interval),
+        }
+    }
+
+    pub fn append(&mut self, batch: &mut ProtocolBatch) {
+        self.checkpoints.append(&mut batch.checkpoints);
+        for entry in batch.entries.drain(..) {
+            self.push_entry(entry);
+        }
+    }
+
+    pub fn push_edit(&mut self, tm: &TuringMachine) {
+        self.push(ProtocolEntry::edit(tm), tm, true);
+        self.generation = next_generation();
+    }
+
+    /// Takes a checkpoint at the last entry, e.g. when the program of the machine is changed.
+    pub fn checkpoint(&mut self, tm: &TuringMachine) {
+        if self.bounds.is_none() || self.is_empty() {
+            return;
+        }
+        let index = self.len - 1;
+        let Some(entry) = self.get(index) else {
+            return;
+        };
+        if self.checkpoints.last().is_some_and(|x| x.index == index) {
+            self.checkpoints.pop();
+        }
+        self.checkpoints.push(Checkpoint {
+            index,
+            entry,
+            tm: tm.without_stats(),
+        });
+    }
+
+    fn push(&mut self, entry: ProtocolEntry, tm: &TuringMachine, is_checkpoint: bool) {
+        if self.bounds.is_some() && is_checkpoint {
+            self.checkpoints.push(Checkpoint {
+                index: self.len,
+                entry: entry.clone(),
+                tm: tm.without_stats(),
+            });
+        }
+        self.push_entry(entry);
+    }
+
+    fn push_entry(&mut self, entry: ProtocolEntry) {
+        if let Some(stream) = &mut self.stream {
+            if let Err(e) = stream.write(self.len, &entry) {
+                self.stream_error = Some(e.to_string());
+                self.stream = None;
+            }
+        }
+        if self.bounds.is_some_and(|x| self.recent.len() == x.recent) {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(entry);
+        self.len += 1;
+    }
+
+    /// Returns the entry, replaying the machine if it is not kept in memory.
+    pub fn get(&self, index: usize) -> Option<ProtocolEntry> {
+        let first = self.len - self.recent.len();
+        if index >= self.len {
+            return None;
+        }
+        if index >= first {
+            return Some(self.recent[index - first].clone());
+        }
+        let n = self.checkpoints.partition_point(|x| x.index <= index);
+        let checkpoint = &self.checkpoints[n.checked_sub(1)?];
+        if checkpoint.index == index {
+            return Some(checkpoint.entry.clone());
+        }
+        let mut tm = checkpoint.tm.clone();
+        for _ in checkpoint.index..index {
+            tm.step();
+        }
+        Some(ProtocolEntry::step(&tm))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ProtocolEntry> + '_ {
+        self.iter_from(0)
+    }
+
+    /// Iterates over the entries from `start`, replaying the machine once from the checkpoint
+    /// before it.
+    pub fn iter_from(&self, start: usize) -> impl Iterator<Item = ProtocolEntry> + '_ {
+        let first = self.len - self.recent.len();
+        let (from, checkpoints) = if start < first {
+            let n = self
+                .checkpoints
+                .partition_point(|x| x.index <= start)
+                .saturating_sub(1);
+            let from = self.checkpoints.get(n).map_or(0, |x| x.index);
+            (from, &self.checkpoints[n..])
+        } else {
+            (start, &[][..])
+        };
+        let mut checkpoints = checkpoints.iter().peekable();
+        let mut tm: Option<TuringMachine> = None;
+        (from..self.len)
+            .map(move |index| {
+                if index >= first {
+                    return self.recent[index - first].clone();
+                }
+                if let Some(checkpoint) = checkpoints.next_if(|x| x.index == index) {
+                    tm = Some(checkpoint.tm.clone());
+                    return checkpoint.entry.clone();
+                }
+                let tm = tm.as_mut().expect("a protocol starts with a checkpoint");
+                tm.step();
+                ProtocolEntry::step(tm)
+            })
+            .skip(start - from)
+    }
+
+    pub fn flush(&mut self) {
+        if let Some(stream) = &mut self.stream {
+            if let Err(e) = stream.writer.flush() {
+                self.stream_error = Some(e.to_string());
+                self.stream = None;
+            }
+        }
+    }
+
+    pub fn take_stream_error(&mut self) -> Option<String> {
+        self.stream_error.take()
+    }
+}
+
+/// Entries of a protocol around the recently shown ones, so that they are not replayed again on
+/// every frame.
+#[derive(Default)]
+pub struct ProtocolWindow {
+    generation: usize,
+    start: usize,
+    entries: Vec<ProtocolEntry>,
+}
+
+impl ProtocolWindow {
+    const LEN: usize = 256;
+
+    pub fn get(&mut self, protocol: &Protocol, index: usize) -> Option<&ProtocolEntry> {
+        let end = self.start + self.entries.len();
+        if self.generation != protocol.generation() || !(self.start..end).contains(&index) {
+            self.generation = protocol.generation();
+            self.start = index.saturating_sub(Self::LEN / 2);
+            self.entries = protocol.iter_from(self.start).take(Self::LEN).collect();
+        }
+        self.entries.get(index - self.start)
+    }
+}
+
+/// Positions of the heads against the step, kept up to date with a protocol.
+#[derive(Default)]
+pub struct Trajectory {
+    first: Option<ProtocolEntry>,
+    len: usize,
+    pub points: Vec<Vec<[f64; 2]>>,
+}
+
+impl Trajectory {
+    /// Largest number of entries in a trajectory.
+    pub const MAX_LEN: usize = 100_000;
+
+    /// Adds the new entries of the protocol, starting anew if the protocol was replaced.
+    pub fn update(&mut self, protocol: &Protocol) {
+        let len = protocol.len().min(Self::MAX_LEN);
+        if len < self.len || (self.len > 0 && protocol.get(0) != self.first) {
+            *self = Self::default();
+        }
+        if len == self.len {
+            return;
+        }
+        if self.len == 0 {
+            self.first = protocol.get(0);
+        }
+        // The new entries are replayed in one pass rather than looked up one by one.
+        let entries = protocol.iter_from(self.len).take(len - self.len);
+        for (index, entry) in (self.len..).zip(entries) {
+            self.points.resize(entry.tapes.len(), Vec::new());
+            for (points, tape) in self.points.iter_mut().zip(&entry.tapes) {
+                points.push([index as f64, tape.position as f64]);
+            }
+        }
+        self.len = len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turing_machine::cell::BLANK_CHAR;
+    use crate::{tm_cmd, tm_cmds};
+
+    fn entries() -> Vec<ProtocolEntry> {
+        let mut tm = TuringMachine::from(
+            "ab",
+            tm_cmds![["q0", 'a', "q0", 'a', 'R'], ["q0", 'b', "q,1", 'c', 'L']],
+        )
+        .unwrap();
+        let mut entries = vec![ProtocolEntry::start(&tm)];
+        while tm.step() {
+            entries.push(ProtocolEntry::step(&tm));
+        }
+        entries
+    }
+
+    fn bounded(recent: usize, interval: usize) -> Protocol {
+        Protocol {
+            bounds: Some(Bounds { recent, interval }),
+            ..Protocol::default()
+        }
+    }
+
+    fn run(protocol: &mut Protocol, input: &str) -> TuringMachine {
+        let mut tm = TuringMachine::from(
+            input,
+            tm_cmds![["q0", 'a', "q0", 'b', 'R'], ["q0", 'b', "q0", 'a', 'R']],
+        )
+        .unwrap();
+        protocol.push_start(&tm);
+        while tm.step() {
+            protocol.push_step(&tm);
+        }
+        tm
+    }
+
+    fn format_protocol(
+        entries: impl IntoIterator<Item = ProtocolEntry>,
+        format: ProtocolFormat,
+    ) -> String {
+        let mut out = Vec::new();
+        write_protocol(&mut out, entries, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn strings(entries: impl IntoIterator<Item = ProtocolEntry>) -> Vec<Vec<String>> {
+        entries.into_iter().map(|x| x.strings()).collect()
+    }
+
+    #[test]
+    fn test_protocol_1() {
+        let mut full = Protocol::default();
+        run(&mut full, "abaabbab");
+        let mut protocol = bounded(3, 4);
+        run(&mut protocol, "abaabbab");
+        assert_eq!(protocol.len(), 9);
+        assert_eq!(protocol.recent.len(), 3);
+        assert_eq!(protocol.checkpoints.len(), 3);
+        assert!(protocol.checkpoints.iter().all(|x| x.tm.stats().steps == 0));
+        let expected = strings(full.iter());
+        assert_eq!(strings(protocol.iter()), expected);
+        let entries = (0..9).map(|n| protocol.get(n).unwrap());
+        assert_eq!(strings(entries), expected);
+        assert!(protocol.get(9).is_none());
+        assert_eq!(protocol.get(5).unwrap().command, Some(1));
+        for start in 0..=10 {
+            let expected = expected.iter().skip(start).cloned().collect::<Vec<_>>();
+            assert_eq!(strings(protocol.iter_from(start)), expected);
+        }
+    }
+
+    #[test]
+    fn test_protocol_2() {
+        let mut protocol = bounded(2, 100);
+        let mut tm = run(&mut protocol, "ab");
+        tm.set_cell(0, 2, 'a'.into()).unwrap();
+        protocol.push_edit(&tm);
+        while tm.step() {
+            protocol.push_step(&tm);
+        }
+        assert_eq!(
+            strings(protocol.iter()),
+            [
+                ["q0ab".to_owned()],
+                ["bq0b".into()],
+                [format!("baq0{BLANK_CHAR}")],
+                ["baq0a".into()],
+                [format!("babq0{BLANK_CHAR}")],
+            ]
+        );
+        assert!(protocol.get(3).unwrap().is_edit);
+        assert_eq!(protocol.get(3).unwrap().strings(), ["baq0a"]);
+    }
+
+    #[test]
+    fn test_protocol_batch() {
+        let mut full = bounded(3, 4);
+        run(&mut full, "abaabbab");
+        let mut protocol = bounded(3, 4);
+        let mut tm = TuringMachine::from(
+            "abaabbab",
+            tm_cmds![["q0", 'a', "q0", 'b', 'R'], ["q0", 'b', "q0", 'a', 'R']],
+        )
+        .unwrap();
+        protocol.push_start(&tm);
+        let mut batch = protocol.batch();
+        for n in 1.. {
+            if !tm.step() {
+                break;
+            }
+            batch.push_step(&tm);
+            if n % 3 == 0 {
+                protocol.append(&mut batch);
+            }
+        }
+        protocol.append(&mut batch);
+        assert_eq!(protocol.len(), full.len());
+        assert_eq!(protocol.checkpoints.len(), full.checkpoints.len());
+        assert_eq!(strings(protocol.iter()), strings(full.iter()));
+    }
+
+    #[test]
+    fn test_protocol_3() {
+        let path = std::env::temp_dir().join(format!("protocol-{}.json", std::process::id()));
+        let mut protocol = Protocol::streaming(&path).unwrap();
+        run(&mut protocol, "ab");
+        protocol.clear();
+        let json = std::fs::read_to_string(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert!(json.starts_with("[\n  {\"step\": 0, \"state\": \"q0\""));
+        assert!(json.ends_with(&format!("\"tapes\": [\"ba{BLANK_CHAR}\"]}}\n]\n")));
+    }
+
+    #[test]
+    fn test_window() {
+        let mut protocol = bounded(3, 4);
+        let mut tm = run(&mut protocol, "abaabbab");
+        let mut window = ProtocolWindow::default();
+        let expected = strings(protocol.iter());
+        for index in (0..9).rev().chain(0..9) {
+            let entry = window.get(&protocol, index).unwrap();
+            assert_eq!(entry.strings(), expected[index]);
+        }
+        assert!(window.get(&protocol, 9).is_none());
+        tm.set_cell(0, 0, 'b'.into()).unwrap();
+        protocol.push_edit(&tm);
+        assert!(window.get(&protocol, 9).unwrap().is_edit);
+        protocol.clear();
+        assert!(window.get(&protocol, 0).is_none());
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let mut protocol = Protocol::default();
+        let mut trajectory = Trajectory::default();
+        trajectory.update(&protocol);
+        assert!(trajectory.points.is_empty());
+        let mut tm = TuringMachine::from(
+            "ab",
+            tm_cmds![
+                ["q0", 'a', "q0", 'a', 'R'],
+                ["q0", 'b', "q1", 'b', 'L'],
+                ["q1", 'a', "q2", 'a', 'L'],
+            ],
+        )
+        .unwrap();
+        protocol.push_start(&tm);
+        tm.step();
+        protocol.push_step(&tm);
+        trajectory.update(&protocol);
+        assert_eq!(trajectory.points, [[[0.0, 0.0], [1.0, 1.0]]]);
+        while tm.step() {
+            protocol.push_step(&tm);
+        }
+        trajectory.update(&protocol);
+        assert_eq!(
+            trajectory.points,
+            [[[0.0, 0.0], [1.0, 1.0], [2.0, 0.0], [3.0, -1.0]]]
+        );
+        protocol.clear();
+        run(&mut protocol, "b");
+        trajectory.update(&protocol);
+        assert_eq!(trajectory.points, [[[0.0, 0.0], [1.0, 1.0]]]);
+    }
+
+    #[test]
+    fn test_trajectory_bounded() {
+        let mut protocol = bounded(3, 4);
+        let mut trajectory = Trajectory::default();
+        let mut tm = TuringMachine::from(
+            "abaabbab",
+            tm_cmds![["q0", 'a', "q0", 'b', 'R'], ["q0", 'b', "q0", 'a', 'R']],
+        )
+        .unwrap();
+        protocol.push_start(&tm);
+        while tm.step() {
+            protocol.push_step(&tm);
+            if protocol.len().is_multiple_of(5) {
+                trajectory.update(&protocol);
+            }
+        }
+        trajectory.update(&protocol);
+        let expected: Vec<_> = (0..9).map(|n| [n as f64, n as f64]).collect();
+        assert_eq!(trajectory.points, [expected]);
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ProtocolFormat::from_path(Path::new("p.CSV")),
+            ProtocolFormat::Csv
+        );
+        assert_eq!(
+            ProtocolFormat::from_path(Path::new("p.json")),
+            ProtocolFormat::Json
+        );
+        assert_eq!(
+            ProtocolFormat::from_path(Path::new("p.htm")),
+            ProtocolFormat::Html
+        );
+        assert_eq!(
+            ProtocolFormat::from_path(Path::new("p")),
+            ProtocolFormat::Text
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            format_protocol(entries(), ProtocolFormat::Text),
+            "q0ab\naq0b\nq,1ac\n"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            format_protocol(entries(), ProtocolFormat::Csv),
+            "step,state,command,edit,head1,tape1
+0,q0,,false,0,ab
+1,q0,1,false,1,ab
+2,\"q,1\",2,false,0,ac
+"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            format_protocol(entries(), ProtocolFormat::Json),
+            r#"[
+  {"step": 0, "state": "q0", "command": null, "edit": false, "heads": [0], "tapes": ["ab"]},
+  {"step": 1, "state": "q0", "command": 1, "edit": false, "heads": [1], "tapes": ["ab"]},
+  {"step": 2, "state": "q,1", "command": 2, "edit": false, "heads": [0], "tapes": ["ac"]}
+]
+"#
+        );
+        assert_eq!(json_string("a\"\\\n\u{1}"), r#""a\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn test_html() {
+        let html = format_protocol(entries(), ProtocolFormat::Html);
+        assert!(html.contains(
+            "<tr><td>1</td><td>q0</td><td>1</td><td>a<span class=\"head\">b</span></td></tr>"
+        ));
+        assert_eq!(html_escape("<a&\">"), "&lt;a&amp;&quot;&gt;");
+    }
+}
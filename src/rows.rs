@@ -0,0 +1,133 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+/// Moves `rows` as a block onto `target` and returns their new indexes.
+///
+/// The block goes after `target` when it is dragged down and before it otherwise.
+pub fn move_rows<T>(items: &mut Vec<T>, rows: &BTreeSet<usize>, target: usize) -> BTreeSet<usize> {
+    let first = match rows.first() {
+        Some(&first) if target < items.len() && !rows.contains(&target) => first,
+        _ => return rows.to_owned(),
+    };
+    let is_down = first < target;
+    let mut moved = Vec::new();
+    let mut rest = Vec::new();
+    let mut position = 0;
+    for (i, item) in items.drain(..).enumerate() {
+        if rows.contains(&i) {
+            moved.push(item);
+        } else {
+            if i == target {
+                position = rest.len() + is_down as usize;
+            }
+            rest.push(item);
+        }
+    }
+    let length = moved.len();
+    rest.splice(position..position, moved);
+    *items = rest;
+    (position..position + length).collect()
+}
+
+/// Inserts copies of `rows` after the last of them and returns the indexes of the copies.
+pub fn duplicate_rows<T: Clone>(items: &mut Vec<T>, rows: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let Some(&last) = rows.last() else {
+        return BTreeSet::new();
+    };
+    let copies: Vec<_> = rows.iter().map(|&i| items[i].clone()).collect();
+    let length = copies.len();
+    items.splice(last + 1..last + 1, copies);
+    (last + 1..last + 1 + length).collect()
+}
+
+pub fn remove_rows<T>(items: &mut Vec<T>, rows: &BTreeSet<usize>) {
+    let mut i = 0;
+    items.retain(|_| {
+        i += 1;
+        !rows.contains(&(i - 1))
+    });
+}
+
+/// Compares strings so that numbers inside them are ordered by value, e.g. `q2` < `q10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a;
+    let mut b = b;
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ordering = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let a_digits = a.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(a.len());
+            let b_digits = b.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(b.len());
+            let a_number = a[..a_digits].trim_start_matches('0');
+            let b_number = b[..b_digits].trim_start_matches('0');
+            let ordering = a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(b_number));
+            a = &a[a_digits..];
+            b = &b[b_digits..];
+            ordering
+        } else {
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+            x.cmp(&y)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(rows: &[usize]) -> BTreeSet<usize> {
+        rows.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_move_rows_1() {
+        let mut items = vec!['a', 'b', 'c', 'd', 'e'];
+        assert_eq!(move_rows(&mut items, &rows(&[0, 2]), 3), rows(&[2, 3]));
+        assert_eq!(items, ['b', 'd', 'a', 'c', 'e']);
+    }
+
+    #[test]
+    fn test_move_rows_2() {
+        let mut items = vec!['a', 'b', 'c', 'd', 'e'];
+        assert_eq!(move_rows(&mut items, &rows(&[3, 4]), 1), rows(&[1, 2]));
+        assert_eq!(items, ['a', 'd', 'e', 'b', 'c']);
+        assert_eq!(move_rows(&mut items, &rows(&[1]), 1), rows(&[1]));
+        assert_eq!(items, ['a', 'd', 'e', 'b', 'c']);
+    }
+
+    #[test]
+    fn test_duplicate_rows() {
+        let mut items = vec!['a', 'b', 'c'];
+        assert_eq!(duplicate_rows(&mut items, &rows(&[0, 1])), rows(&[2, 3]));
+        assert_eq!(items, ['a', 'b', 'a', 'b', 'c']);
+        assert_eq!(duplicate_rows(&mut items, &rows(&[])), rows(&[]));
+    }
+
+    #[test]
+    fn test_remove_rows() {
+        let mut items = vec!['a', 'b', 'c', 'd'];
+        remove_rows(&mut items, &rows(&[0, 2]));
+        assert_eq!(items, ['b', 'd']);
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("q2", "q10"), Ordering::Less);
+        assert_eq!(natural_cmp("q10", "q10"), Ordering::Equal);
+        assert_eq!(natural_cmp("q010", "q9"), Ordering::Greater);
+        assert_eq!(natural_cmp("q", "q0"), Ordering::Less);
+        assert_eq!(natural_cmp("qz", "q9"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,237 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::Result;
+use eframe::egui::{ecolor::Hsva, Color32, ColorImage};
+
+use crate::protocol::ProtocolEntry;
+use crate::turing_machine::cell::Cell;
+
+/// Largest number of steps in a diagram.
+pub const MAX_ROWS: usize = 10_000;
+/// Side of a cell in pixels when shown or exported.
+pub const CELL_SIZE: usize = 4;
+
+const BLANK_COLOR: Color32 = Color32::WHITE;
+const SEPARATOR_COLOR: Color32 = Color32::DARK_GRAY;
+
+pub struct SpaceTime {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color32>,
+    /// Colors of the head in each state, in order of appearance.
+    pub states: Vec<(String, Color32)>,
+}
+
+impl SpaceTime {
+    /// Lays the tapes of the first [`MAX_ROWS`] entries out side by side.
+    pub fn new(entries: impl IntoIterator<Item = ProtocolEntry>) -> Self {
+        let entries: Vec<_> = entries.into_iter().take(MAX_ROWS).collect();
+        let num_tapes = entries.first().map_or(0, |x| x.tapes.len());
+        // Leftmost position and number of columns of each tape.
+        let mut ranges: Vec<Option<(isize, isize)>> = vec![None; num_tapes];
+        for entry in &entries {
+            for (range, tape) in ranges.iter_mut().zip(&entry.tapes) {
+                let first = tape.position - tape.head as isize;
+                let last = first + tape.cells.len() as isize - 1;
+                *range = Some(match *range {
+                    Some((min, max)) => (min.min(first), max.max(last)),
+                    None => (first, last),
+                });
+            }
+        }
+        let ranges: Vec<_> = ranges.into_iter().flatten().collect();
+        let width = ranges
+            .iter()
+            .map(|(min, max)| (max - min + 1) as usize)
+            .sum::<usize>()
+            + ranges.len().saturating_sub(1);
+        let mut pixels = Vec::with_capacity(width * entries.len());
+        let mut symbols: Vec<String> = Vec::new();
+        let mut states: Vec<(String, Color32)> = Vec::new();
+        for entry in &entries {
+            let state = match states.iter().position(|(x, _)| *x == entry.state) {
+                Some(n) => states[n].1,
+                None => {
+                    let color = palette(states.len(), 0.9, 0.75);
+                    states.push((entry.state.to_owned(), color));
+                    color
+                }
+            };
+            for (n, (tape, &(min, max))) in entry.tapes.iter().zip(&ranges).enumerate() {
+                if n > 0 {
+                    pixels.push(SEPARATOR_COLOR);
+                }
+                let first = tape.position - tape.head as isize;
+                for position in min..=max {
+                    let index = position - first;
+                    if index == tape.head as isize {
+                        pixels.push(state);
+                        continue;
+                    }
+                    let cell = usize::try_from(index)
+                        .ok()
+                        .and_then(|x| tape.cells.get(x))
+                        .unwrap_or(&Cell::Blank);
+                    let color = match cell {
+                        Cell::Blank => BLANK_COLOR,
+                        Cell::Symbol(_) => {
+                            let name = cell.to_string_with_blank(tape.blank);
+                            let n = match symbols.iter().position(|x| *x == name) {
+                                Some(n) => n,
+                                None => {
+                                    symbols.push(name);
+                                    symbols.len() - 1
+                                }
+                            };
+                            palette(n, 0.3, 0.95)
+                        }
+                    };
+                    pixels.push(color);
+                }
+            }
+        }
+        Self {
+            width,
+            height: entries.len(),
+            pixels,
+            states,
+        }
+    }
+
+    pub fn to_image(&self, cell_size: usize) -> ColorImage {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * cell_size * cell_size);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            for _ in 0..cell_size {
+                for &color in row {
+                    pixels.extend(std::iter::repeat_n(color, cell_size));
+                }
+            }
+        }
+        ColorImage {
+            size: [self.width * cell_size, self.height * cell_size],
+            pixels,
+        }
+    }
+
+    pub fn write_png(&self, path: &Path, cell_size: usize) -> Result<()> {
+        let image = self.to_image(cell_size);
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(file),
+            image.width() as u32,
+            image.height() as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<_> = image
+            .pixels
+            .iter()
+            .flat_map(|x| [x.r(), x.g(), x.b()])
+            .collect();
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Returns the `n`th of the colors with evenly spread hues.
+fn palette(n: usize, saturation: f32, value: f32) -> Color32 {
+    // The golden ratio keeps neighboring colors apart for any number of them.
+    let hue = (n as f32 * 0.618_034).fract();
+    Hsva::new(hue, saturation, value, 1.0).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turing_machine::core::{Direction, MultiCommand};
+    use crate::turing_machine::TuringMachine;
+    use crate::{tm_cmd, tm_cmds};
+
+    fn entries(tm: &mut TuringMachine) -> Vec<ProtocolEntry> {
+        let mut entries = vec![ProtocolEntry::start(tm)];
+        while tm.step() {
+            entries.push(ProtocolEntry::step(tm));
+        }
+        entries
+    }
+
+    #[test]
+    fn test_1() {
+        let commands = tm_cmds![
+            ["q0", 'a', "q0", 'a', 'R'],
+            ["q0", 'b', "q0", 'b', 'R'],
+            ["q0", Cell::Blank, "q1", 'a', 'L'],
+        ];
+        let mut tm =
+            TuringMachine::from_multi(&["ab"], commands.into_iter().map(Into::into).collect())
+                .unwrap();
+        let space_time = SpaceTime::new(entries(&mut tm));
+        assert_eq!((space_time.width, space_time.height), (3, 4));
+        let states: Vec<_> = space_time.states.iter().map(|x| x.0.as_str()).collect();
+        assert_eq!(states, ["q0", "q1"]);
+        let q0 = space_time.states[0].1;
+        let q1 = space_time.states[1].1;
+        // The head hides `a` in the first row.
+        let b = palette(0, 0.3, 0.95);
+        let a = palette(1, 0.3, 0.95);
+        assert_eq!(
+            space_time.pixels,
+            [
+                [q0, b, BLANK_COLOR],
+                [a, q0, BLANK_COLOR],
+                [a, b, q0],
+                [a, q1, a],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_2() {
+        let mut tm = TuringMachine::from_multi(
+            &["a", ""],
+            vec![MultiCommand::new(
+                "q0".into(),
+                vec!['a'.into(), Cell::Blank],
+                "q1".into(),
+                vec!['a'.into(), 'a'.into()],
+                vec![Direction::Left, Direction::None],
+            )
+            .unwrap()],
+        )
+        .unwrap();
+        let space_time = SpaceTime::new(entries(&mut tm));
+        assert_eq!((space_time.width, space_time.height), (4, 2));
+        assert_eq!(space_time.pixels[2], SEPARATOR_COLOR);
+        let image = space_time.to_image(3);
+        assert_eq!(image.size, [12, 6]);
+        assert_eq!(image.pixels[2 * 3], SEPARATOR_COLOR);
+        assert_eq!(image.pixels[12 * 2 + 2 * 3 + 2], SEPARATOR_COLOR);
+    }
+
+    #[test]
+    fn test_png() {
+        let commands = tm_cmds![["q0", 'a', "q0", 'b', 'R']];
+        let mut tm =
+            TuringMachine::from_multi(&["aa"], commands.into_iter().map(Into::into).collect())
+                .unwrap();
+        let space_time = SpaceTime::new(entries(&mut tm));
+        let path = std::env::temp_dir().join(format!("space-time-{}.png", std::process::id()));
+        space_time.write_png(&path, 2).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (6, 6));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        drop(reader);
+        _ = std::fs::remove_file(&path);
+    }
+}
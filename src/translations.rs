@@ -22,62 +22,258 @@ impl AppLanguage {
 zoom = Zoom
 alphabet-primary = Primary Alphabet
 alphabet-secondary = Secondary Alphabet
+blank = Blank Symbol
+symbol-new = new
+symbol-remove = Remove symbol
+classes = Symbol Classes
+class-add = Add class
+class-remove = Remove class
+pattern-any = Any symbol
+pattern-non-blank = Any symbol except blank
+pattern-same = Same as read
+pattern-class-write = Symbol of this class at the position of the read symbol in its class
+undo = Undo
+redo = Redo
+label-modified = (modified)
+confirm-preset-title = Replace Program
+confirm-preset = The preset replaces the current program, and its unsaved changes will be lost.
+btn-replace = Replace
+btn-cancel = Cancel
+view-table = Table
+view-expanded = Expanded
+view-matrix = Matrix
+view-plot = Plot
+view-trajectory = Head trajectory
+view-diagram = State diagram
+view-statistics = Statistics
+view-space-time = Space-time
+stats-none = Statistics are shown when the machine stops.
+stats-stopped = Stopped
+stats-final-state = Final state
+stats-no-command = No command for
+stats-halt = Halt
+stats-tape = Tape
+stats-extent = Max extent
+stats-moves = Head moves
+stats-visited = Cells visited
+stats-visits = Visits
+stats-fires = Fires
+view-crossings = Crossings
+crossings-first-tape = Crossing sequences are recorded for the first tape.
+crossings-off = Crossing sequences are recorded only for runs started with this view open.
+crossings-boundary = Boundary
+crossings-length = Length
+crossings-sequence = States
+crossings-max-length = Max Crossing Sequence
+label-coverage = Coverage:
+coverage-reset = Reset
+coverage-none = No coverage recorded
+matrix-template = Generated by a template
+matrix-add = Add command
+matrix-delete = Delete command
+commands = Commands
 input = Input
+head = Head
+start-state = Start State
 command-add = Add command
 command-remove = Remove command
+command-duplicate = Duplicate
+command-insert-above = Insert above
+command-insert-below = Insert below
+command-delete = Delete
+commands-sort = Sort by state
+commands-copy = Copy
+filter = Filter
+filter-all = All
+filter-read = Read
+filter-write = Write
+state-rename = Rename state
+state-rename-hint = The new name must be non-empty and not used yet, must not start with # and must not contain spaces, :, -> or ~
+btn-rename = Rename
+states-renumber = Renumber states
+commands-paste = Paste…
+commands-paste-title = Paste Commands
+commands-paste-hint = One command per line, e.g. q0 a -> q1 b R
+btn-insert = Insert
+line = line
+err-unknown-symbol = unknown symbol or class
+err-no-commands = no commands
+err-no-start-state = No commands mention the start state, so the first state is selected instead of
 tape-add = Add tape
 tape-remove = Remove tape
+tape-to-one = Simulate on one tape
+err-one-tape-start = Simulation on one tape needs empty auxiliary tapes and all heads at zero
+alphabet-to-binary = Reduce alphabet to binary
 stop = Stop
 start = Start
+continue = Continue
 protocol-save = Save protocol
+label-steps = Steps
+label-per-second = per second
+protocol-stream = Stream the protocol to a file
+protocol-stream-hint = Only the recent steps are kept in memory, and the older ones are replayed when shown.
+export = Export
+export-dot = Graphviz DOT
+export-latex-table = LaTeX table of commands
+export-tikz = TikZ state diagram
+export-latex-protocol = LaTeX protocol
+export-space-time = Space-time diagram (PNG)
+program-save = Save program
+program-load = Load program
 ok-file-saved = The file was saved successfully
+ok-file-loaded = The file was loaded successfully
 plotting-stop = Stop plotting
 plotting-start = Start plotting
 label-presets = presets
 line-complexity = Complexity
 axis-length-of-number = Length of Word
 axis-max-steps = Max Steps
+axis-step = Step
+axis-head-position = Head Position
 err-no-protocol = No protocol
 err-no-path-given = No path given
 err-failed-to-create-open = Failed to create/open:
 err-failed-to-write = Failed to write to
+err-failed-to-read = Failed to read
 col-state = State
 col-cell = Cell
 col-dir = Dir
 col-protocol = Protocol
 btn-change-language = Change Language
 label-number-sign = #
+label-manual-edit = Manual edit
+current-state = Current State
+symbol = Symbol
+btn-move-head = Move head here
 "#;
 
     const FTL_RU: &str = r#"
 zoom = Масштаб
 alphabet-primary = Основной алфавит
 alphabet-secondary = Дополнительный алфавит
+blank = Пустой символ
+symbol-new = новый
+symbol-remove = Удалить символ
+classes = Классы символов
+class-add = Добавить класс
+class-remove = Удалить класс
+pattern-any = Любой символ
+pattern-non-blank = Любой символ, кроме пустого
+pattern-same = Как прочитанный
+pattern-class-write = Символ этого класса на месте прочитанного символа в его классе
+undo = Отменить
+redo = Повторить
+label-modified = (изменено)
+confirm-preset-title = Заменить программу
+confirm-preset = Предустановка заменит текущую программу, и несохранённые изменения будут потеряны.
+btn-replace = Заменить
+btn-cancel = Отмена
+view-table = Таблица
+view-expanded = Развёрнутые
+view-matrix = Матрица
+view-plot = График
+view-trajectory = Траектория головки
+view-diagram = Диаграмма состояний
+view-statistics = Статистика
+view-space-time = Пространство-время
+stats-none = Статистика показывается, когда машина остановлена.
+stats-stopped = Остановлена
+stats-final-state = Конечное состояние
+stats-no-command = Нет команды для
+stats-halt = Останов
+stats-tape = Лента
+stats-extent = Макс. длина
+stats-moves = Сдвигов головки
+stats-visited = Посещено ячеек
+stats-visits = Посещений
+stats-fires = Срабатываний
+view-crossings = Пересечения
+crossings-first-tape = Последовательности пересечений записываются для первой ленты.
+crossings-off = Последовательности пересечений записываются только для запусков, начатых при открытом виде.
+crossings-boundary = Граница
+crossings-length = Длина
+crossings-sequence = Состояния
+crossings-max-length = Макс. последовательность пересечений
+label-coverage = Покрытие:
+coverage-reset = Сбросить
+coverage-none = Покрытие не записано
+matrix-template = Получено из шаблона
+matrix-add = Добавить команду
+matrix-delete = Удалить команду
+commands = Команды
 input = Ввод
+head = Головка
+start-state = Начальное состояние
 command-add = Добавить команду
 command-remove = Удалить команду
+command-duplicate = Дублировать
+command-insert-above = Вставить выше
+command-insert-below = Вставить ниже
+command-delete = Удалить
+commands-sort = Сортировать по состоянию
+commands-copy = Копировать
+filter = Фильтр
+filter-all = Всё
+filter-read = Чтение
+filter-write = Запись
+state-rename = Переименовать состояние
+state-rename-hint = Новое имя должно быть непустым и ещё не занятым, не начинаться с # и не содержать пробелов, :, -> и ~
+btn-rename = Переименовать
+states-renumber = Перенумеровать состояния
+commands-paste = Вставить…
+commands-paste-title = Вставка команд
+commands-paste-hint = По одной команде в строке, например q0 a -> q1 b R
+btn-insert = Вставить
+line = строка
+err-unknown-symbol = неизвестный символ или класс
+err-no-commands = нет команд
+err-no-start-state = Начальное состояние не встречается в командах, поэтому выбрано первое состояние вместо
 tape-add = Добавить ленту
 tape-remove = Удалить ленту
+tape-to-one = Моделировать на одной ленте
+err-one-tape-start = Для моделирования на одной ленте вспомогательные ленты должны быть пусты, а все головки стоять в нуле
+alphabet-to-binary = Свести алфавит к двоичному
 stop = Стоп
 start = Старт
+continue = Продолжить
 protocol-save = Сохранить протокол
+label-steps = Шагов
+label-per-second = в секунду
+protocol-stream = Записывать протокол в файл
+protocol-stream-hint = В памяти хранятся только последние шаги, а более ранние воспроизводятся при показе.
+export = Экспорт
+export-dot = Graphviz DOT
+export-latex-table = Таблица команд LaTeX
+export-tikz = Диаграмма состояний TikZ
+export-latex-protocol = Протокол LaTeX
+export-space-time = Диаграмма пространство-время (PNG)
+program-save = Сохранить программу
+program-load = Загрузить программу
 ok-file-saved = Файл был сохранён успешно
+ok-file-loaded = Файл был загружен успешно
 plotting-stop = Остановить построение графика
 plotting-start = Начать построение графика
 label-presets = пресеты
 line-complexity = Сложность
 axis-length-of-number = Длина слова
 axis-max-steps = Максимальное количество шагов
+axis-step = Шаг
+axis-head-position = Позиция головки
 err-no-protocol = Нет протокола
 err-no-path-given = Путь не задан
 err-failed-to-create-open = Не удалость создать/открыть:
 err-failed-to-write = Не удалось записать в
+err-failed-to-read = Не удалось прочитать
 col-state = Сост.
 col-cell = Ячейка
 col-dir = Направ.
 col-protocol = Протокол
 btn-change-language = Сменить язык
 label-number-sign = №
+label-manual-edit = Ручное изменение
+current-state = Текущее состояние
+symbol = Символ
+btn-move-head = Переместить головку сюда
 "#;
 
     pub fn build_or_default(s: &str) -> Self {
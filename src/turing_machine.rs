@@ -4,110 +4,213 @@
 
 use anyhow::{anyhow, Result};
 
-use self::core::{Command, Direction, MultiCommand, Program};
-use self::tape::Tape;
+use self::cell::Cell;
+#[cfg(test)]
+use self::cell::BLANK_CHAR;
+#[cfg(test)]
+use self::core::{build_program, Command, MultiCommand};
+use self::core::{Direction, Program};
+use self::stats::{HaltReason, RunStats};
+use self::tape::{Tape, TapeSnapshot};
 
+pub mod binary;
 pub mod cell;
 pub mod core;
+#[cfg(test)]
+mod simulation;
+pub mod single_tape;
+pub mod stats;
+pub mod symbol;
 pub mod tape;
 
+#[derive(Clone)]
 pub struct TuringMachine {
     tapes: Vec<Tape>,
     current_state: String,
     program: Program,
+    blank: char,
+    last_command: Option<usize>,
+    stats: RunStats,
+    halt_reason: Option<HaltReason>,
 }
 
 impl TuringMachine {
-    pub fn new(start_tapes: &[&str], start_state: String, program: Program) -> Result<Self> {
-        let length = match program.values().nth(0) {
-            Some(instructions) => match instructions.keys().nth(0) {
-                Some(cells) => cells.len(),
-                None => return Err(anyhow!("there's no instructions")),
-            },
-            None => return Err(anyhow!("there's no instructions")),
-        };
+    #[cfg(test)]
+    pub fn new(
+        start_tapes: &[&str],
+        start_heads: &[isize],
+        start_state: String,
+        program: Program,
+    ) -> Result<Self> {
+        Self::with_blank(start_tapes, start_heads, start_state, BLANK_CHAR, program)
+    }
+
+    /// `blank` denotes blank cells in the start tapes and in the rendering of the tapes.
+    pub fn with_blank(
+        start_tapes: &[&str],
+        start_heads: &[isize],
+        start_state: String,
+        blank: char,
+        program: Program,
+    ) -> Result<Self> {
+        let length = program
+            .num_tapes()
+            .ok_or_else(|| anyhow!("there's no instructions"))?;
         if length != start_tapes.len() {
             return Err(anyhow!("length of instructions and tapes does not equal"));
         }
-        for instructions in program.values() {
-            for (icells, (_, ocells, directions)) in instructions {
-                if length != icells.len() || length != ocells.len() || length != directions.len() {
-                    return Err(anyhow!("invalid instructions length"));
-                }
-            }
+        if start_heads.len() != start_tapes.len() {
+            return Err(anyhow!("length of heads and tapes does not equal"));
         }
+        program.check_num_tapes(length)?;
         let mut tapes = Vec::new();
-        for &i in start_tapes {
-            tapes.push(Tape::from(i));
+        for (&i, &head) in start_tapes.iter().zip(start_heads) {
+            let mut tape = Tape::with_blank(i, blank);
+            tape.set_head(head);
+            tapes.push(tape);
         }
         Ok(Self {
+            stats: RunStats::new(&tapes, &start_state, false),
             tapes,
             current_state: start_state,
             program,
+            blank,
+            last_command: None,
+            halt_reason: None,
         })
     }
 
+    #[cfg(test)]
     pub fn from(start_tape: &str, commands: Vec<Command>) -> Result<Self> {
         let start_state = match commands.first() {
             Some(cmd) => cmd.istate.to_owned(),
             None => return Err(anyhow!("no commands")),
         };
-        let mut program = Program::new();
-        for cmd in commands {
-            program.entry(cmd.istate).or_default().insert(
-                vec![cmd.icell],
-                (cmd.ostate, vec![cmd.ocell], vec![cmd.direction]),
-            );
-        }
+        let program = build_program(commands.into_iter().map(MultiCommand::from).collect())?;
+        let tapes = vec![Tape::from(start_tape)];
         Ok(Self {
-            tapes: vec![Tape::from(start_tape)],
+            stats: RunStats::new(&tapes, &start_state, false),
+            tapes,
             current_state: start_state,
             program,
+            blank: BLANK_CHAR,
+            last_command: None,
+            halt_reason: None,
         })
     }
 
+    #[cfg(test)]
     pub fn from_multi(start_tapes: &[&str], commands: Vec<MultiCommand>) -> Result<Self> {
-        let length = match commands.first() {
-            Some(c) => c.len(),
+        let start_state = match commands.first() {
+            Some(c) => c.istate.to_owned(),
             None => return Err(anyhow!("no commands")),
         };
-        let start_state = commands[0].istate.to_owned();
-        if length != start_tapes.len() {
-            return Err(anyhow!("invalid tapes length"));
-        }
-        let mut program = Program::new();
-        for cmd in commands {
-            if length != cmd.len() {
-                return Err(anyhow!("invalid tapes length"));
-            }
-            let (istate, icells, ostate, ocells, directions) = cmd.unpack();
-            program
-                .entry(istate)
-                .or_default()
-                .insert(icells, (ostate, ocells, directions));
-        }
-        let mut tapes = Vec::new();
-        for &i in start_tapes {
-            tapes.push(Tape::from(i));
-        }
-        Ok(Self {
-            tapes,
-            current_state: start_state,
+        let program = build_program(commands)?;
+        Self::new(
+            start_tapes,
+            &vec![0; start_tapes.len()],
+            start_state,
             program,
-        })
+        )
     }
 
-    pub fn restart(&mut self, start_tapes: &[&str], start_state: String) -> Result<()> {
+    pub fn restart(
+        &mut self,
+        start_tapes: &[&str],
+        start_heads: &[isize],
+        start_state: String,
+    ) -> Result<()> {
         if start_tapes.len() != self.tapes.len() {
             return Err(anyhow!("invalid start tapes"));
         }
-        for (n, &i) in start_tapes.iter().enumerate() {
-            self.tapes[n] = Tape::from(i);
+        if start_heads.len() != self.tapes.len() {
+            return Err(anyhow!("invalid start heads"));
+        }
+        for (n, (&i, &head)) in start_tapes.iter().zip(start_heads).enumerate() {
+            self.tapes[n] = Tape::with_blank(i, self.blank);
+            self.tapes[n].set_head(head);
         }
         self.current_state = start_state;
+        self.last_command = None;
+        let records_crossings = self.stats.crossings.is_some();
+        self.stats = RunStats::new(&self.tapes, &self.current_state, records_crossings);
+        self.halt_reason = None;
         Ok(())
     }
 
+    pub fn set_program(&mut self, program: Program) -> Result<()> {
+        program.check_num_tapes(self.tapes.len())?;
+        self.program = program;
+        self.halt_reason = None;
+        Ok(())
+    }
+
+    pub fn tapes(&self) -> &[Tape] {
+        &self.tapes
+    }
+
+    pub fn current_state(&self) -> &str {
+        &self.current_state
+    }
+
+    /// Returns the index of the command applied by the last step.
+    pub fn last_command(&self) -> Option<usize> {
+        self.last_command
+    }
+
+    pub fn stats(&self) -> &RunStats {
+        &self.stats
+    }
+
+    /// Starts or stops recording the crossing sequences, which grow with every move.
+    pub fn record_crossings(&mut self, is_recorded: bool) {
+        self.stats.crossings = is_recorded.then(Default::default);
+    }
+
+    /// Returns a copy of the machine with the statistics started anew, e.g. to replay it.
+    pub fn without_stats(&self) -> Self {
+        Self {
+            tapes: self.tapes.clone(),
+            current_state: self.current_state.clone(),
+            program: self.program.clone(),
+            blank: self.blank,
+            last_command: self.last_command,
+            stats: RunStats::new(&self.tapes, &self.current_state, false),
+            halt_reason: self.halt_reason.clone(),
+        }
+    }
+
+    pub fn halt_reason(&self) -> Option<&HaltReason> {
+        self.halt_reason.as_ref()
+    }
+
+    pub fn set_current_state(&mut self, state: String) {
+        self.current_state = state;
+        self.halt_reason = None;
+    }
+
+    pub fn set_cell(&mut self, tape: usize, position: isize, cell: Cell) -> Result<()> {
+        match self.tapes.get_mut(tape) {
+            Some(t) => {
+                t.set(position, cell);
+                self.halt_reason = None;
+                Ok(())
+            }
+            None => Err(anyhow!("invalid tape index")),
+        }
+    }
+
+    pub fn set_head(&mut self, tape: usize, position: isize) -> Result<()> {
+        match self.tapes.get_mut(tape) {
+            Some(t) => {
+                t.set_head(position);
+                self.halt_reason = None;
+                Ok(())
+            }
+            None => Err(anyhow!("invalid tape index")),
+        }
+    }
+
     pub fn to_strings(&self) -> Vec<String> {
         let mut strings = Vec::new();
         for tape in &self.tapes {
@@ -115,26 +218,32 @@ impl TuringMachine {
         }
         strings
     }
-}
 
-impl Iterator for TuringMachine {
-    type Item = Vec<String>;
+    pub fn snapshots(&self) -> Vec<TapeSnapshot> {
+        self.tapes.iter().map(Tape::snapshot).collect()
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let instructions = match self.program.get(&self.current_state) {
-            Some(i) => i,
-            None => return None,
-        };
+    /// Makes a step and returns `false` if the machine has halted.
+    pub fn step(&mut self) -> bool {
         let icells: Vec<_> = self
             .tapes
             .iter()
             .map(|tape| tape.get().to_owned())
             .collect();
-        let (state, ocells, directions) = match instructions.get(&icells) {
-            Some(v) => v,
-            None => return None,
+        let Some(transition) = self.program.get(&self.current_state, &icells) else {
+            self.halt_reason = Some(if self.program.has_state(&self.current_state) {
+                HaltReason::NoCommand(icells)
+            } else {
+                HaltReason::FinalState
+            });
+            return false;
         };
-        for (tape, (&cell, direction)) in self.tapes.iter_mut().zip(ocells.iter().zip(directions)) {
+        let directions = transition.directions;
+        for (tape, (&cell, direction)) in self
+            .tapes
+            .iter_mut()
+            .zip(transition.ocells.iter().zip(directions))
+        {
             tape.write(cell);
             match direction {
                 Direction::Left => tape.left(),
@@ -142,16 +251,30 @@ impl Iterator for TuringMachine {
                 Direction::Right => tape.right(),
             }
         }
-        self.current_state = state.to_owned();
-        let strings = self.to_strings();
-        Some(strings)
+        self.current_state = transition.ostate.to_owned();
+        self.last_command = transition.command;
+        self.stats.record_step(
+            &self.tapes,
+            &self.current_state,
+            directions,
+            transition.command,
+        );
+        true
+    }
+}
+
+impl Iterator for TuringMachine {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().then(|| self.to_strings())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::turing_machine::cell::{Cell, BLANK_CHAR};
-    use crate::turing_machine::core::Instructions;
+    use crate::turing_machine::core::{CellPattern, CellWrite, Instructions};
+    use crate::{tm_cmd, tm_cmds, tm_mcmd};
 
     use super::*;
 
@@ -160,11 +283,11 @@ mod tests {
         let program = Program::from([(
             "q0".into(),
             Instructions::from([(
-                vec![Cell::Symbol('a')],
-                ("q0".into(), vec![Cell::Symbol('b')], vec![Direction::Right]),
+                vec![Cell::from('a')],
+                ("q0".into(), vec![Cell::from('b')], vec![Direction::Right]),
             )]),
         )]);
-        let mut tm = TuringMachine::new(&["aaa"], "q0".into(), program).unwrap();
+        let mut tm = TuringMachine::new(&["aaa"], &[0], "q0".into(), program).unwrap();
         assert_eq!(tm.to_strings(), vec!["q0aaa"]);
         assert_eq!(tm.next(), Some(vec!["bq0aa".into()]));
         assert_eq!(tm.next(), Some(vec!["bbq0a".into()]));
@@ -179,12 +302,12 @@ mod tests {
                 "q0".into(),
                 Instructions::from([
                     (
-                        vec![Cell::Symbol('0')],
-                        ("q0".into(), vec![Cell::Symbol('1')], vec![Direction::Right]),
+                        vec![Cell::from('0')],
+                        ("q0".into(), vec![Cell::from('1')], vec![Direction::Right]),
                     ),
                     (
-                        vec![Cell::Symbol('1')],
-                        ("q0".into(), vec![Cell::Symbol('0')], vec![Direction::Right]),
+                        vec![Cell::from('1')],
+                        ("q0".into(), vec![Cell::from('0')], vec![Direction::Right]),
                     ),
                     (
                         vec![Cell::Blank],
@@ -196,12 +319,12 @@ mod tests {
                 "q1".into(),
                 Instructions::from([
                     (
-                        vec![Cell::Symbol('0')],
-                        ("q1".into(), vec![Cell::Symbol('0')], vec![Direction::Left]),
+                        vec![Cell::from('0')],
+                        ("q1".into(), vec![Cell::from('0')], vec![Direction::Left]),
                     ),
                     (
-                        vec![Cell::Symbol('1')],
-                        ("q1".into(), vec![Cell::Symbol('1')], vec![Direction::Left]),
+                        vec![Cell::from('1')],
+                        ("q1".into(), vec![Cell::from('1')], vec![Direction::Left]),
                     ),
                     (
                         vec![Cell::Blank],
@@ -210,7 +333,7 @@ mod tests {
                 ]),
             ),
         ]);
-        let mut tm = TuringMachine::new(&["101101"], "q0".into(), program).unwrap();
+        let mut tm = TuringMachine::new(&["101101"], &[0], "q0".into(), program).unwrap();
         assert_eq!(tm.to_strings(), vec!["q0101101"]);
         assert_eq!(tm.next().unwrap(), vec!["0q001101"]);
         assert_eq!(tm.next().unwrap(), vec!["01q01101"]);
@@ -235,9 +358,9 @@ mod tests {
             "aaa",
             vec![Command::new(
                 "q0".into(),
-                Cell::Symbol('a'),
+                Cell::from('a'),
                 "q0".into(),
-                Cell::Symbol('b'),
+                Cell::from('b'),
                 Direction::Right,
             )],
         )
@@ -254,16 +377,16 @@ mod tests {
         let cmds = vec![
             Command::new(
                 "q0".into(),
-                Cell::Symbol('0'),
+                Cell::from('0'),
                 "q0".into(),
-                Cell::Symbol('1'),
+                Cell::from('1'),
                 Direction::Right,
             ),
             Command::new(
                 "q0".into(),
-                Cell::Symbol('1'),
+                Cell::from('1'),
                 "q0".into(),
-                Cell::Symbol('0'),
+                Cell::from('0'),
                 Direction::Right,
             ),
             Command::new(
@@ -275,16 +398,16 @@ mod tests {
             ),
             Command::new(
                 "q1".into(),
-                Cell::Symbol('0'),
+                Cell::from('0'),
                 "q1".into(),
-                Cell::Symbol('0'),
+                Cell::from('0'),
                 Direction::Left,
             ),
             Command::new(
                 "q1".into(),
-                Cell::Symbol('1'),
+                Cell::from('1'),
                 "q1".into(),
-                Cell::Symbol('1'),
+                Cell::from('1'),
                 Direction::Left,
             ),
             Command::new(
@@ -313,4 +436,202 @@ mod tests {
         assert_eq!(tm.next().unwrap(), vec!["qz010010"]);
         assert_eq!(tm.next(), None);
     }
+
+    #[test]
+    fn test_5() {
+        let cmds = vec![MultiCommand::from(Command::new(
+            "q0".into(),
+            Cell::from('a'),
+            "q0".into(),
+            Cell::from('b'),
+            Direction::Left,
+        ))];
+        let program = build_program(cmds).unwrap();
+        let mut tm = TuringMachine::new(&["aaa"], &[2], "q0".into(), program).unwrap();
+        assert_eq!(tm.to_strings(), vec!["aaq0a"]);
+        assert_eq!(tm.next(), Some(vec!["aq0ab".into()]));
+        tm.restart(&["aaa"], &[1], "q0".into()).unwrap();
+        assert_eq!(tm.to_strings(), vec!["aq0aa"]);
+        assert!(tm.restart(&["aaa"], &[1, 2], "q0".into()).is_err());
+    }
+
+    #[test]
+    fn test_6() {
+        let program = build_program(vec![MultiCommand::new(
+            "q0".into(),
+            vec![Cell::Blank, Cell::from('x')],
+            "qz".into(),
+            vec![Cell::from('y'), Cell::Blank],
+            vec![Direction::None, Direction::None],
+        )
+        .unwrap()])
+        .unwrap();
+        let mut tm = TuringMachine::new(&["", "ax"], &[0, 1], "q0".into(), program).unwrap();
+        assert_eq!(
+            tm.next(),
+            Some(vec!["qzy".into(), format!("aqz{BLANK_CHAR}")])
+        );
+        assert_eq!(tm.next(), None);
+    }
+
+    #[test]
+    fn test_7() {
+        let cmds = tm_cmds![["q0", 'a', "q0", 'b', 'R'], ["q1", 'X', "qz", 'Y', 'N']];
+        let mut tm =
+            TuringMachine::from_multi(&["aaa"], cmds.into_iter().map(MultiCommand::from).collect())
+                .unwrap();
+        assert_eq!(tm.next(), Some(vec!["bq0aa".into()]));
+        tm.set_cell(0, 1, Cell::from('X')).unwrap();
+        tm.set_head(0, 2).unwrap();
+        assert_eq!(tm.to_strings(), vec!["bXq0a"]);
+        assert_eq!(tm.next(), Some(vec![format!("bXbq0{BLANK_CHAR}")]));
+        tm.set_head(0, 1).unwrap();
+        tm.set_current_state("q1".into());
+        assert_eq!(tm.current_state(), "q1");
+        assert_eq!(tm.tapes()[0].head(), 1);
+        assert_eq!(tm.next(), Some(vec!["bqzYb".into()]));
+        assert_eq!(tm.next(), None);
+        assert!(tm.set_cell(1, 0, Cell::Blank).is_err());
+        assert!(tm.set_head(1, 0).is_err());
+    }
+
+    #[test]
+    fn test_8() {
+        let mut tm = TuringMachine::from("ab", tm_cmds![["q0", 'a', "q0", 'a', 'R']]).unwrap();
+        let program = build_program(vec![tm_mcmd!(
+            "q0",
+            ['b', 'b'],
+            "q0",
+            ['b', 'b'],
+            ['R', 'R']
+        )])
+        .unwrap();
+        assert!(tm.set_program(program).is_err());
+        let program = build_program(vec![tm_mcmd!("q0", ['a'], "q0", ['c'], ['R'])]).unwrap();
+        tm.set_program(program).unwrap();
+        assert_eq!(tm.next(), Some(vec!["cq0b".into()]));
+    }
+
+    #[test]
+    fn test_9() {
+        let program = build_program(vec![
+            tm_mcmd!("q0", ['a'], "q0", [BLANK_CHAR], ['R']),
+            tm_mcmd!("q0", [Cell::Blank], "qz", ['a'], ['N']),
+        ])
+        .unwrap();
+        let mut tm = TuringMachine::with_blank(&["a_a"], &[0], "q0".into(), '_', program).unwrap();
+        assert_eq!(tm.to_strings(), vec!["q0a_a"]);
+        assert_eq!(tm.next(), Some(vec![format!("{BLANK_CHAR}q0_a")]));
+        assert_eq!(tm.next(), Some(vec![format!("{BLANK_CHAR}qzaa")]));
+        assert_eq!(tm.next(), None);
+        tm.restart(&["_a"], &[0], "q0".into()).unwrap();
+        assert_eq!(tm.next(), Some(vec!["qzaa".into()]));
+    }
+
+    #[test]
+    fn test_10() {
+        let program = build_program(vec![
+            tm_mcmd!("q0", ["a'"], "q0", ["X1"], ['R']),
+            tm_mcmd!("q0", ['a'], "q0", ["#0"], ['R']),
+            tm_mcmd!("q0", [Cell::Blank], "qz", [Cell::Blank], ['L']),
+        ])
+        .unwrap();
+        let mut tm = TuringMachine::new(&["{a'}a"], &[0], "q0".into(), program).unwrap();
+        assert_eq!(tm.to_strings(), vec!["q0{a'}a"]);
+        assert_eq!(tm.next(), Some(vec!["{X1}q0a".into()]));
+        assert_eq!(tm.next(), Some(vec![format!("{{X1}}{{#0}}q0{BLANK_CHAR}")]));
+        assert_eq!(tm.next(), Some(vec!["{X1}qz{#0}".into()]));
+        assert_eq!(tm.next(), None);
+    }
+
+    #[test]
+    fn test_11() {
+        let program = build_program(vec![
+            MultiCommand::with_patterns(
+                "q0".into(),
+                vec![CellPattern::NonBlank, CellPattern::Any],
+                "q0".into(),
+                vec![CellWrite::Same, 'x'.into()],
+                vec![Direction::Right, Direction::Right],
+            )
+            .unwrap(),
+            tm_mcmd!(
+                "q0",
+                ['b', Cell::Blank],
+                "qz",
+                ['b', Cell::Blank],
+                ['N', 'N']
+            ),
+        ])
+        .unwrap();
+        let mut tm = TuringMachine::new(&["ab", ""], &[0, 0], "q0".into(), program).unwrap();
+        assert_eq!(
+            tm.next(),
+            Some(vec!["aq0b".into(), format!("xq0{BLANK_CHAR}")])
+        );
+        assert_eq!(
+            tm.next(),
+            Some(vec!["aqzb".into(), format!("xqz{BLANK_CHAR}")])
+        );
+        assert_eq!(tm.next(), None);
+    }
+
+    #[test]
+    fn test_12() {
+        let mut tm = TuringMachine::from(
+            "ab",
+            vec![
+                tm_cmd!("q0", 'a', "q0", 'a', 'R'),
+                tm_cmd!("q0", 'b', "q1", 'b', 'R'),
+            ],
+        )
+        .unwrap();
+        assert_eq!(tm.last_command(), None);
+        assert!(tm.step());
+        assert_eq!(tm.last_command(), Some(0));
+        assert!(tm.step());
+        assert_eq!(tm.last_command(), Some(1));
+        assert!(!tm.step());
+        assert_eq!(tm.last_command(), Some(1));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut tm = TuringMachine::from(
+            "aab",
+            vec![
+                tm_cmd!("q0", 'a', "q0", 'a', 'R'),
+                tm_cmd!("q0", 'b', "q1", 'b', 'L'),
+                tm_cmd!("q1", 'a', "q2", 'c', 'N'),
+                tm_cmd!("q2", 'b', "q2", 'b', 'N'),
+            ],
+        )
+        .unwrap();
+        tm.record_crossings(true);
+        while tm.step() {}
+        let stats = tm.stats();
+        assert_eq!(stats.steps, 4);
+        assert_eq!(stats.head_moves, [3]);
+        assert_eq!(stats.cells_visited(), [3]);
+        assert_eq!(stats.max_extents, [3]);
+        assert_eq!(stats.command_fires, [2, 1, 1]);
+        assert_eq!(stats.state_visits["q0"], 3);
+        assert_eq!(stats.state_visits["q2"], 1);
+        let crossings = stats.crossings.as_ref().unwrap();
+        let sequences: Vec<_> = crossings.iter().collect();
+        assert_eq!(sequences, [(1, vec!["q0"]), (2, vec!["q0", "q1"])]);
+        assert_eq!(crossings.max_len(), 2);
+        assert_eq!(
+            tm.halt_reason(),
+            Some(&HaltReason::NoCommand(vec!['c'.into()]))
+        );
+        tm.set_current_state("q3".into());
+        assert_eq!(tm.halt_reason(), None);
+        assert!(!tm.step());
+        assert_eq!(tm.halt_reason(), Some(&HaltReason::FinalState));
+        tm.restart(&["a"], &[0], "q0".into()).unwrap();
+        assert_eq!(tm.stats().steps, 0);
+        assert_eq!(tm.stats().crossings.as_ref().unwrap().max_len(), 0);
+        assert_eq!(tm.without_stats().stats().crossings, None);
+    }
 }
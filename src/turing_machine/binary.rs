@@ -0,0 +1,349 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use super::cell::Cell;
+use super::core::{build_program_with_classes, Direction, MultiCommand, Program, SymbolClass};
+use super::single_tape::{product, SEPARATOR};
+use super::symbol::{split_names, Symbol};
+#[cfg(test)]
+use super::tape::Tape;
+
+/// A step reads the blocks to the right, writes them back to the left and then moves by whole
+/// blocks.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Phase {
+    Read(Vec<Vec<Cell>>),
+    Write(Vec<Vec<Cell>>, usize),
+    Move(usize),
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SimState {
+    state: String,
+    directions: Vec<Direction>,
+    phase: Phase,
+}
+
+/// Program over `0`, `1` and blank that simulates a program over any alphabet, with each cell
+/// written as a block of `width` cells and the heads at the starts of blocks.
+pub struct Binary {
+    pub commands: Vec<MultiCommand>,
+    /// Number of cells of a block.
+    pub width: usize,
+    codes: HashMap<Cell, Vec<Cell>>,
+    blank: char,
+}
+
+impl Binary {
+    pub fn new(
+        commands: &[MultiCommand],
+        classes: &[SymbolClass],
+        cells: &[Cell],
+        start: &str,
+        blank: char,
+    ) -> Result<Self> {
+        let mut names = commands.iter().flat_map(|x| [&x.istate, &x.ostate]);
+        if start.contains(SEPARATOR) || names.any(|x| x.contains(SEPARATOR)) {
+            return Err(anyhow!("state names must not contain {SEPARATOR:?}"));
+        }
+        if matches!(blank, '0' | '1') {
+            return Err(anyhow!("the blank symbol must not be 0 or 1"));
+        }
+        let num_tapes = commands.first().map_or(1, MultiCommand::len);
+        let mut symbols: Vec<Cell> = Vec::new();
+        for &cell in cells {
+            if cell != Cell::Blank && !symbols.contains(&cell) {
+                symbols.push(cell);
+            }
+        }
+        let width = (usize::BITS - symbols.len().saturating_sub(1).leading_zeros()).max(1) as usize;
+        let mut codes = HashMap::from([(Cell::Blank, vec![Cell::Blank; width])]);
+        for (n, &symbol) in symbols.iter().enumerate() {
+            let code = (0..width)
+                .rev()
+                .map(|bit| if (n >> bit) & 1 == 1 { '1' } else { '0' }.into())
+                .collect();
+            codes.insert(symbol, code);
+        }
+        let mut compiler = Compiler {
+            program: build_program_with_classes(commands.to_owned(), classes)?,
+            num_tapes,
+            width,
+            symbols: codes
+                .iter()
+                .map(|(&cell, code)| (code.to_owned(), cell))
+                .collect(),
+            codes,
+            blank,
+            commands: Vec::new(),
+        };
+        compiler.simulate(start)?;
+        Ok(Self {
+            commands: compiler.commands,
+            width,
+            codes: compiler.codes,
+            blank,
+        })
+    }
+
+    pub fn symbols() -> [Symbol; 2] {
+        ['0', '1'].map(Symbol::from)
+    }
+
+    /// Encodes an input written as for `Tape::with_blank`.
+    pub fn encode(&self, input: &str) -> Result<String> {
+        let mut encoded = String::new();
+        for name in split_names(input) {
+            let cell = Cell::from_name(name, self.blank);
+            let code = self
+                .codes
+                .get(&cell)
+                .ok_or_else(|| anyhow!("{name:?} is not in the alphabet"))?;
+            for cell in code {
+                encoded += &cell.to_string_with_blank(self.blank);
+            }
+        }
+        Ok(encoded)
+    }
+
+    /// Decodes the tape, leaving blank the blocks that are not codes of symbols.
+    #[cfg(test)]
+    pub fn decode(&self, tape: &Tape) -> Tape {
+        let width = self.width as isize;
+        let mut decoded = Tape::with_blank("", self.blank);
+        let range = tape.range();
+        for block in range.start().div_euclid(width)..=range.end().div_euclid(width) {
+            let code: Vec<_> = (0..width)
+                .map(|n| *tape.get_at(block * width + n))
+                .collect();
+            let symbol = self.codes.iter().find(|x| *x.1 == code).map(|x| *x.0);
+            decoded.set(block, symbol.unwrap_or(Cell::Blank));
+        }
+        decoded.set_head(tape.head().div_euclid(width));
+        decoded
+    }
+}
+
+struct Compiler {
+    program: Program,
+    num_tapes: usize,
+    width: usize,
+    codes: HashMap<Cell, Vec<Cell>>,
+    symbols: HashMap<Vec<Cell>, Cell>,
+    blank: char,
+    commands: Vec<MultiCommand>,
+}
+
+impl Compiler {
+    fn simulate(&mut self, start: &str) -> Result<()> {
+        let bits = [Cell::Blank, '0'.into(), '1'.into()];
+        let reads = product(&vec![bits.to_vec(); self.num_tapes]);
+        let start = SimState {
+            state: start.to_owned(),
+            directions: Vec::new(),
+            phase: Phase::Read(vec![Vec::new(); self.num_tapes]),
+        };
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = vec![start];
+        let mut n = 0;
+        while n < queue.len() {
+            let from = queue[n].clone();
+            n += 1;
+            let from_name = self.name(&from);
+            for icells in &reads {
+                let Some((ocells, directions, to)) = self.next(&from, icells) else {
+                    continue;
+                };
+                self.commands.push(MultiCommand::new(
+                    from_name.to_owned(),
+                    icells.to_owned(),
+                    self.name(&to),
+                    ocells,
+                    directions,
+                )?);
+                if seen.insert(to.clone()) {
+                    queue.push(to);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next(
+        &self,
+        from: &SimState,
+        icells: &[Cell],
+    ) -> Option<(Vec<Cell>, Vec<Direction>, SimState)> {
+        let to = |state: &str, directions: &[Direction], phase| SimState {
+            state: state.to_owned(),
+            directions: directions.to_owned(),
+            phase,
+        };
+        let read = || {
+            to(
+                &from.state,
+                &[],
+                Phase::Read(vec![Vec::new(); self.num_tapes]),
+            )
+        };
+        let last = self.width - 1;
+        let res = match &from.phase {
+            Phase::Read(blocks) if blocks[0].len() < last => {
+                let mut blocks = blocks.to_owned();
+                for (block, &cell) in blocks.iter_mut().zip(icells) {
+                    block.push(cell);
+                }
+                let directions = vec![Direction::Right; self.num_tapes];
+                (
+                    icells.to_owned(),
+                    directions,
+                    to(&from.state, &[], Phase::Read(blocks)),
+                )
+            }
+            Phase::Read(blocks) => {
+                let mut symbols = Vec::new();
+                for (block, &cell) in blocks.iter().zip(icells) {
+                    let mut code = block.to_owned();
+                    code.push(cell);
+                    symbols.push(*self.symbols.get(&code)?);
+                }
+                let transition = self.program.get(&from.state, &symbols)?;
+                let blocks: Vec<_> = transition
+                    .ocells
+                    .iter()
+                    .map(|x| self.codes.get(x).cloned())
+                    .collect::<Option<_>>()?;
+                let ocells = blocks.iter().map(|x| x[last]).collect();
+                let (ostate, directions) = (transition.ostate, transition.directions);
+                if last == 0 {
+                    let next = to(ostate, &[], Phase::Read(vec![Vec::new(); self.num_tapes]));
+                    (ocells, directions.to_owned(), next)
+                } else {
+                    let next = to(ostate, directions, Phase::Write(blocks, last - 1));
+                    (ocells, vec![Direction::Left; self.num_tapes], next)
+                }
+            }
+            Phase::Write(blocks, n) => {
+                let ocells = blocks.iter().map(|x| x[*n]).collect();
+                if *n > 0 {
+                    let directions = vec![Direction::Left; self.num_tapes];
+                    let next = to(
+                        &from.state,
+                        &from.directions,
+                        Phase::Write(blocks.to_owned(), n - 1),
+                    );
+                    (ocells, directions, next)
+                } else if last == 0 {
+                    (ocells, from.directions.to_owned(), read())
+                } else {
+                    let next = to(&from.state, &from.directions, Phase::Move(last));
+                    (ocells, from.directions.to_owned(), next)
+                }
+            }
+            Phase::Move(n) => {
+                let next = if *n > 1 {
+                    to(&from.state, &from.directions, Phase::Move(n - 1))
+                } else {
+                    read()
+                };
+                (icells.to_owned(), from.directions.to_owned(), next)
+            }
+        };
+        Some(res)
+    }
+
+    fn name(&self, state: &SimState) -> String {
+        let blocks = |blocks: &[Vec<Cell>]| {
+            let blocks: Vec<String> = blocks
+                .iter()
+                .map(|x| x.iter().map(|x| x.name(self.blank)).collect())
+                .collect();
+            blocks.join(",")
+        };
+        let directions: String = state.directions.iter().map(|x| x.to_string()).collect();
+        let phase = match &state.phase {
+            Phase::Read(read) if read.iter().all(Vec::is_empty) => return state.state.to_owned(),
+            Phase::Read(read) => format!("r[{}]", blocks(read)),
+            Phase::Write(write, n) => format!("w{n}[{};{directions}]", blocks(write)),
+            Phase::Move(n) => format!("m{n}[{directions}]"),
+        };
+        format!("{}{SEPARATOR}{phase}", state.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_cmd;
+    use crate::turing_machine::cell::BLANK_CHAR;
+    use crate::turing_machine::core::CellPattern;
+    use crate::turing_machine::simulation;
+
+    fn check(commands: Vec<MultiCommand>, alphabet: &str, symbols: &str, length: usize) {
+        let num_tapes = commands[0].len();
+        let cells: Vec<Cell> = alphabet
+            .chars()
+            .chain(symbols.chars())
+            .map(Cell::from)
+            .collect();
+        let start = commands[0].istate.to_owned();
+        let binary = Binary::new(&commands, &[], &cells, &start, BLANK_CHAR).unwrap();
+        let binary_cells = [Cell::Blank, '0'.into(), '1'.into()];
+        for cmd in &binary.commands {
+            assert!(cmd
+                .icells()
+                .iter()
+                .all(|x| matches!(x, CellPattern::Cell(x) if binary_cells.contains(x))));
+        }
+        let alphabet: Vec<_> = alphabet.chars().collect();
+        simulation::check(
+            commands,
+            binary.commands.to_owned(),
+            &start,
+            &alphabet,
+            length,
+            |word| {
+                let mut tapes = vec![String::new(); num_tapes];
+                tapes[0] = binary.encode(word).unwrap();
+                tapes
+            },
+            |tapes| tapes.iter().map(|x| binary.decode(x)).collect(),
+        );
+    }
+
+    #[test]
+    fn test_one_tape() {
+        check(crate::Application::preset_one_tape(), "abc", "01ABC", 5);
+    }
+
+    #[test]
+    fn test_multitape() {
+        check(crate::Application::preset_multitape(), "abc", "01X", 5);
+    }
+
+    #[test]
+    fn test_width() {
+        let commands: Vec<MultiCommand> = vec![tm_cmd!("q0", 'a', "q1", 'b', 'R').into()];
+        let binary = Binary::new(&commands, &[], &['a'.into()], "q0", '_').unwrap();
+        assert_eq!(binary.width, 1);
+        assert_eq!(binary.encode("a_a").unwrap(), "0_0");
+        let cells: Vec<Cell> = "abcde".chars().map(Cell::from).collect();
+        let binary = Binary::new(&commands, &[], &cells, "q0", '_').unwrap();
+        assert_eq!(binary.width, 3);
+        assert_eq!(binary.encode("eb").unwrap(), "100001");
+        assert!(binary.encode("f").is_err());
+    }
+
+    #[test]
+    fn test_blank() {
+        let commands: Vec<MultiCommand> = vec![tm_cmd!("q0", 'a', "q1", 'b', 'R').into()];
+        for blank in ['0', '1'] {
+            assert!(Binary::new(&commands, &[], &['a'.into()], "q0", blank).is_err());
+        }
+    }
+}
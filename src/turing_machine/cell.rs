@@ -2,29 +2,76 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use std::fmt;
+
+use anyhow::Result;
+
+use super::symbol::Symbol;
+
+/// Default display symbol of the blank cell.
 pub const BLANK_CHAR: char = '\u{03BB}'; // Lambda.
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Cell {
     Blank,
-    Symbol(char),
+    Symbol(Symbol),
 }
 
-impl From<Cell> for char {
-    fn from(value: Cell) -> Self {
-        match value {
-            Cell::Blank => BLANK_CHAR,
-            Cell::Symbol(ch) => ch,
+impl Cell {
+    pub fn from_name(name: &str, blank: char) -> Self {
+        let mut chars = name.chars();
+        if chars.next() == Some(blank) && chars.next().is_none() {
+            Self::Blank
+        } else {
+            Self::Symbol(Symbol::new(name))
+        }
+    }
+
+    /// Same as [`Cell::from_name`], but validates the name of a symbol.
+    pub fn parse(name: &str, blank: char) -> Result<Self> {
+        match Self::from_name(name, blank) {
+            Self::Blank => Ok(Self::Blank),
+            Self::Symbol(_) => Symbol::parse(name).map(Self::Symbol),
+        }
+    }
+
+    /// Returns the name of the cell without braces.
+    pub fn name(self, blank: char) -> String {
+        match self {
+            Self::Blank => blank.to_string(),
+            Self::Symbol(symbol) => symbol.name().to_owned(),
         }
     }
+
+    pub fn to_string_with_blank(self, blank: char) -> String {
+        match self {
+            Self::Blank => blank.to_string(),
+            Self::Symbol(symbol) => symbol.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_string_with_blank(BLANK_CHAR).fmt(f)
+    }
+}
+
+impl From<Symbol> for Cell {
+    fn from(value: Symbol) -> Self {
+        Self::Symbol(value)
+    }
 }
 
 impl From<char> for Cell {
     fn from(value: char) -> Self {
-        match value {
-            BLANK_CHAR => Self::Blank,
-            _ => Self::Symbol(value),
-        }
+        Self::Symbol(value.into())
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Self::Symbol(value.into())
     }
 }
 
@@ -39,40 +86,56 @@ mod tests {
 
     #[test]
     fn test_2() {
-        assert_eq!(Cell::Symbol('a'), Cell::Symbol('a'));
+        assert_eq!(Cell::from('a'), Cell::from('a'));
     }
 
     #[test]
     fn test_3() {
-        assert_ne!(Cell::Symbol('a'), Cell::Symbol('b'));
+        assert_ne!(Cell::from('a'), Cell::from('b'));
     }
 
     #[test]
     fn test_4() {
-        assert_ne!(Cell::Symbol('a'), Cell::Blank);
+        assert_ne!(Cell::from('a'), Cell::Blank);
     }
 
     #[test]
     fn test_5() {
-        let x: char = Cell::Blank.into();
-        assert_eq!(x, BLANK_CHAR);
+        assert_eq!(Cell::Blank.to_string(), BLANK_CHAR.to_string());
     }
 
     #[test]
     fn test_6() {
-        let x: char = Cell::Symbol('a').into();
-        assert_eq!(x, 'a');
+        assert_eq!(Cell::from('a').to_string(), "a");
     }
 
     #[test]
     fn test_7() {
         let x: Cell = 'a'.into();
-        assert_eq!(x, Cell::Symbol('a'));
+        assert_eq!(x, Cell::Symbol(Symbol::new("a")));
     }
 
     #[test]
     fn test_8() {
         let x: Cell = BLANK_CHAR.into();
-        assert_eq!(x, Cell::Blank);
+        assert_eq!(x, Cell::Symbol(Symbol::from(BLANK_CHAR)));
+    }
+
+    #[test]
+    fn test_9() {
+        assert_eq!(Cell::from_name("_", '_'), Cell::Blank);
+        assert_eq!(Cell::from_name("__", '_'), Cell::from("__"));
+        assert_eq!(
+            Cell::from_name(&BLANK_CHAR.to_string(), '_'),
+            Cell::from(BLANK_CHAR)
+        );
+    }
+
+    #[test]
+    fn test_10() {
+        assert_eq!(Cell::Blank.to_string_with_blank('#'), "#");
+        assert_eq!(Cell::from('a').to_string_with_blank('#'), "a");
+        assert_eq!(Cell::from("X1").to_string_with_blank('#'), "{X1}");
+        assert_eq!(Cell::from("X1").name('#'), "X1");
     }
 }
@@ -2,14 +2,16 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 use anyhow::{anyhow, Result};
 
 use super::cell::Cell;
+use super::symbol::Symbol;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     Left,
     None,
@@ -37,8 +39,334 @@ impl Direction {
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CellPattern {
+    Cell(Cell),
+    Any,
+    NonBlank,
+    Class(String),
+}
+
+impl CellPattern {
+    pub const ANY: &'static str = "{*}";
+    pub const NON_BLANK: &'static str = "{+}";
+
+    pub fn parse(name: &str, blank: char) -> Result<Self> {
+        match name {
+            Self::ANY => Ok(Self::Any),
+            Self::NON_BLANK => Ok(Self::NonBlank),
+            _ => match name
+                .strip_prefix(Symbol::OPEN)
+                .and_then(|s| s.strip_suffix(Symbol::CLOSE))
+            {
+                Some(class) if SymbolClass::is_name_valid(class) => Ok(Self::Class(class.into())),
+                Some(_) => Err(anyhow!("invalid class: {name:?}")),
+                None => Cell::parse(name, blank).map(Self::Cell),
+            },
+        }
+    }
+
+    pub fn name(&self, blank: char) -> String {
+        match self {
+            Self::Cell(cell) => cell.name(blank),
+            Self::Any => Self::ANY.into(),
+            Self::NonBlank => Self::NON_BLANK.into(),
+            Self::Class(class) => format!("{}{class}{}", Symbol::OPEN, Symbol::CLOSE),
+        }
+    }
+
+    /// An exact cell ranks above a class, and a class above a wildcard.
+    fn rank(&self) -> usize {
+        match self {
+            Self::Cell(_) => 3,
+            Self::Class(_) => 2,
+            Self::NonBlank => 1,
+            Self::Any => 0,
+        }
+    }
+}
+
+impl From<Cell> for CellPattern {
+    fn from(value: Cell) -> Self {
+        Self::Cell(value)
+    }
+}
+
+impl From<char> for CellPattern {
+    fn from(value: char) -> Self {
+        Self::Cell(value.into())
+    }
+}
+
+impl From<&str> for CellPattern {
+    fn from(value: &str) -> Self {
+        Self::Cell(value.into())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CellWrite {
+    Cell(Cell),
+    Same,
+    /// Writes the cell of the named [`SymbolClass`] at the position of the read cell in the class
+    /// read from the same tape, e.g. `{abc}` to `{ABC}` maps `b` to `B`.
+    Class(String),
+}
+
+impl CellWrite {
+    pub const SAME: &'static str = "{=}";
+
+    pub fn parse(name: &str, blank: char) -> Result<Self> {
+        if name == Self::SAME {
+            return Ok(Self::Same);
+        }
+        match name
+            .strip_prefix(Symbol::OPEN)
+            .and_then(|s| s.strip_suffix(Symbol::CLOSE))
+        {
+            Some(class) if SymbolClass::is_name_valid(class) => Ok(Self::Class(class.into())),
+            Some(_) => Err(anyhow!("invalid class: {name:?}")),
+            None => Cell::parse(name, blank).map(Self::Cell),
+        }
+    }
+
+    pub fn name(&self, blank: char) -> String {
+        match self {
+            Self::Cell(cell) => cell.name(blank),
+            Self::Same => Self::SAME.into(),
+            Self::Class(class) => format!("{}{class}{}", Symbol::OPEN, Symbol::CLOSE),
+        }
+    }
+}
+
+impl From<Cell> for CellWrite {
+    fn from(value: Cell) -> Self {
+        Self::Cell(value)
+    }
+}
+
+impl From<char> for CellWrite {
+    fn from(value: char) -> Self {
+        Self::Cell(value.into())
+    }
+}
+
+impl From<&str> for CellWrite {
+    fn from(value: &str) -> Self {
+        Self::Cell(value.into())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SymbolClass {
+    pub name: String,
+    pub cells: Vec<Cell>,
+}
+
+impl SymbolClass {
+    pub fn is_name_valid(name: &str) -> bool {
+        !["*", "+", "="].contains(&name) && Symbol::parse(name).is_ok()
+    }
+
+    fn find<'a>(classes: &'a [Self], name: &str) -> Result<&'a Self> {
+        classes
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow!("unknown class: {name:?}"))
+    }
+}
+
 pub type Instructions = HashMap<Vec<Cell>, (String, Vec<Cell>, Vec<Direction>)>;
-pub type Program = HashMap<String, Instructions>;
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Cell(Cell),
+    Any,
+    NonBlank,
+    Set(HashSet<Cell>),
+}
+
+impl Matcher {
+    fn matches(&self, cell: Cell) -> bool {
+        match self {
+            Self::Cell(c) => *c == cell,
+            Self::Any => true,
+            Self::NonBlank => cell != Cell::Blank,
+            Self::Set(cells) => cells.contains(&cell),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Writer {
+    Cell(Cell),
+    Same,
+    Map(HashMap<Cell, Cell>),
+}
+
+impl Writer {
+    fn new(icell: &CellPattern, ocell: &CellWrite, classes: &[SymbolClass]) -> Result<Self> {
+        match (icell, ocell) {
+            (_, CellWrite::Cell(cell)) => Ok(Self::Cell(*cell)),
+            (_, CellWrite::Same) => Ok(Self::Same),
+            (CellPattern::Class(from), CellWrite::Class(to)) => {
+                let from = SymbolClass::find(classes, from)?;
+                let to = SymbolClass::find(classes, to)?;
+                if from.cells.len() != to.cells.len() {
+                    return Err(anyhow!(
+                        "classes {:?} and {:?} differ in size",
+                        from.name,
+                        to.name
+                    ));
+                }
+                Ok(Self::Map(
+                    from.cells
+                        .iter()
+                        .copied()
+                        .zip(to.cells.iter().copied())
+                        .collect(),
+                ))
+            }
+            (_, CellWrite::Class(to)) => {
+                Err(anyhow!("class {to:?} is written without a class read"))
+            }
+        }
+    }
+
+    fn write(&self, read: Cell) -> Cell {
+        match self {
+            Self::Cell(cell) => *cell,
+            Self::Same => read,
+            Self::Map(map) => map.get(&read).copied().unwrap_or(read),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Instruction {
+    ostate: String,
+    ocells: Vec<Cell>,
+    directions: Vec<Direction>,
+    command: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    command: usize,
+    icells: Vec<Matcher>,
+    ostate: String,
+    ocells: Vec<Writer>,
+    directions: Vec<Direction>,
+    rank: Vec<usize>,
+}
+
+/// Commands that read exact cells are looked up first, then the others from the most specific
+/// one by [`MultiCommand::rank`]. Commands of equal rank keep their order.
+#[derive(Clone, Debug, Default)]
+pub struct Program {
+    instructions: HashMap<String, HashMap<Vec<Cell>, Instruction>>,
+    rules: HashMap<String, Vec<Rule>>,
+}
+
+pub struct Transition<'a> {
+    pub ostate: &'a str,
+    pub ocells: Vec<Cell>,
+    pub directions: &'a [Direction],
+    /// Index of the command the transition comes from, if the program was built from commands.
+    pub command: Option<usize>,
+}
+
+impl Program {
+    pub fn get(&self, state: &str, icells: &[Cell]) -> Option<Transition<'_>> {
+        if let Some(instruction) = self
+            .instructions
+            .get(state)
+            .and_then(|instructions| instructions.get(icells))
+        {
+            return Some(Transition {
+                ostate: &instruction.ostate,
+                ocells: instruction.ocells.to_owned(),
+                directions: &instruction.directions,
+                command: instruction.command,
+            });
+        }
+        let rule = self.rules.get(state)?.iter().find(|rule| {
+            rule.icells
+                .iter()
+                .zip(icells)
+                .all(|(matcher, &cell)| matcher.matches(cell))
+        })?;
+        let ocells = rule
+            .ocells
+            .iter()
+            .zip(icells)
+            .map(|(writer, &cell)| writer.write(cell))
+            .collect();
+        Some(Transition {
+            ostate: &rule.ostate,
+            ocells,
+            directions: &rule.directions,
+            command: Some(rule.command),
+        })
+    }
+
+    pub fn has_state(&self, state: &str) -> bool {
+        self.instructions.contains_key(state) || self.rules.contains_key(state)
+    }
+
+    pub fn num_tapes(&self) -> Option<usize> {
+        match self.instructions.values().flat_map(HashMap::keys).next() {
+            Some(icells) => Some(icells.len()),
+            None => self.rules.values().flatten().next().map(|r| r.icells.len()),
+        }
+    }
+
+    pub fn check_num_tapes(&self, length: usize) -> Result<()> {
+        let is_instruction_valid =
+            self.instructions
+                .values()
+                .flatten()
+                .all(|(icells, instruction)| {
+                    length == icells.len()
+                        && length == instruction.ocells.len()
+                        && length == instruction.directions.len()
+                });
+        let is_rule_valid = self.rules.values().flatten().all(|rule| {
+            length == rule.icells.len()
+                && length == rule.ocells.len()
+                && length == rule.directions.len()
+        });
+        if is_instruction_valid && is_rule_valid {
+            Ok(())
+        } else {
+            Err(anyhow!("invalid instructions length"))
+        }
+    }
+}
+
+impl<const N: usize> From<[(String, Instructions); N]> for Program {
+    fn from(value: [(String, Instructions); N]) -> Self {
+        let instructions = value.into_iter().map(|(state, instructions)| {
+            let instructions = instructions
+                .into_iter()
+                .map(|(icells, (ostate, ocells, directions))| {
+                    let instruction = Instruction {
+                        ostate,
+                        ocells,
+                        directions,
+                        command: None,
+                    };
+                    (icells, instruction)
+                })
+                .collect();
+            (state, instructions)
+        });
+        Self {
+            instructions: instructions.collect(),
+            rules: HashMap::new(),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Command {
@@ -70,9 +398,9 @@ impl Command {
 #[derive(Clone, Debug, PartialEq)]
 pub struct MultiCommand {
     pub istate: String,
-    icells: Vec<Cell>,
+    icells: Vec<CellPattern>,
     pub ostate: String,
-    ocells: Vec<Cell>,
+    ocells: Vec<CellWrite>,
     directions: Vec<Direction>,
     length: usize,
 }
@@ -84,6 +412,22 @@ impl MultiCommand {
         ostate: String,
         ocells: Vec<Cell>,
         directions: Vec<Direction>,
+    ) -> Result<Self> {
+        Self::with_patterns(
+            istate,
+            icells.into_iter().map(CellPattern::Cell).collect(),
+            ostate,
+            ocells.into_iter().map(CellWrite::Cell).collect(),
+            directions,
+        )
+    }
+
+    pub fn with_patterns(
+        istate: String,
+        icells: Vec<CellPattern>,
+        ostate: String,
+        ocells: Vec<CellWrite>,
+        directions: Vec<Direction>,
     ) -> Result<Self> {
         let length = icells.len();
         if length == 0 || length != ocells.len() || length != directions.len() {
@@ -101,8 +445,8 @@ impl MultiCommand {
     }
 
     pub fn add_tape(&mut self) {
-        self.icells.push(Cell::Blank);
-        self.ocells.push(Cell::Blank);
+        self.icells.push(CellPattern::Cell(Cell::Blank));
+        self.ocells.push(CellWrite::Cell(Cell::Blank));
         self.directions.push(Direction::None);
         self.length += 1;
     }
@@ -117,11 +461,23 @@ impl MultiCommand {
         self.length -= 1;
     }
 
-    pub fn get_mut_icell(&mut self, n: usize) -> Option<&mut Cell> {
+    pub fn icells(&self) -> &[CellPattern] {
+        &self.icells
+    }
+
+    pub fn ocells(&self) -> &[CellWrite] {
+        &self.ocells
+    }
+
+    pub fn directions(&self) -> &[Direction] {
+        &self.directions
+    }
+
+    pub fn get_mut_icell(&mut self, n: usize) -> Option<&mut CellPattern> {
         self.icells.get_mut(n)
     }
 
-    pub fn get_mut_ocell(&mut self, n: usize) -> Option<&mut Cell> {
+    pub fn get_mut_ocell(&mut self, n: usize) -> Option<&mut CellWrite> {
         self.ocells.get_mut(n)
     }
 
@@ -129,7 +485,15 @@ impl MultiCommand {
         self.directions.get_mut(n)
     }
 
-    pub fn unpack(self) -> (String, Vec<Cell>, String, Vec<Cell>, Vec<Direction>) {
+    pub fn unpack(
+        self,
+    ) -> (
+        String,
+        Vec<CellPattern>,
+        String,
+        Vec<CellWrite>,
+        Vec<Direction>,
+    ) {
         (
             self.istate,
             self.icells,
@@ -142,21 +506,222 @@ impl MultiCommand {
     pub fn len(&self) -> usize {
         self.length
     }
+
+    /// Returns the read cells if the command reads no patterns.
+    pub fn exact_icells(&self) -> Option<Vec<Cell>> {
+        self.icells
+            .iter()
+            .map(|pattern| match pattern {
+                CellPattern::Cell(cell) => Some(*cell),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ranks of the patterns, tape by tape.
+    fn rank(&self) -> Vec<usize> {
+        self.icells.iter().map(CellPattern::rank).collect()
+    }
+
+    fn writers(&self, classes: &[SymbolClass]) -> Result<Vec<Writer>> {
+        self.icells
+            .iter()
+            .zip(&self.ocells)
+            .map(|(icell, ocell)| Writer::new(icell, ocell, classes))
+            .collect()
+    }
 }
 
 impl From<Command> for MultiCommand {
     fn from(value: Command) -> Self {
         Self {
             istate: value.istate,
-            icells: vec![value.icell],
+            icells: vec![value.icell.into()],
             ostate: value.ostate,
-            ocells: vec![value.ocell],
+            ocells: vec![value.ocell.into()],
             directions: vec![value.direction],
             length: 1,
         }
     }
 }
 
+#[cfg(test)]
+pub fn build_program(commands: Vec<MultiCommand>) -> Result<Program> {
+    build_program_with_classes(commands, &[])
+}
+
+pub fn build_program_with_classes(
+    commands: Vec<MultiCommand>,
+    classes: &[SymbolClass],
+) -> Result<Program> {
+    let length = match commands.first() {
+        Some(c) => c.len(),
+        None => return Err(anyhow!("no commands")),
+    };
+    let mut program = Program::default();
+    for (command, cmd) in commands.into_iter().enumerate() {
+        if length != cmd.len() {
+            return Err(anyhow!("invalid tapes length"));
+        }
+        let writers = cmd.writers(classes)?;
+        let exact = cmd.exact_icells();
+        let rank = cmd.rank();
+        let (istate, icells, ostate, _, directions) = cmd.unpack();
+        if let Some(icells) = exact {
+            let ocells = writers
+                .iter()
+                .zip(&icells)
+                .map(|(writer, &cell)| writer.write(cell))
+                .collect();
+            program.instructions.entry(istate).or_default().insert(
+                icells,
+                Instruction {
+                    ostate,
+                    ocells,
+                    directions,
+                    command: Some(command),
+                },
+            );
+            continue;
+        }
+        let icells = icells
+            .into_iter()
+            .map(|pattern| match pattern {
+                CellPattern::Cell(cell) => Ok(Matcher::Cell(cell)),
+                CellPattern::Any => Ok(Matcher::Any),
+                CellPattern::NonBlank => Ok(Matcher::NonBlank),
+                CellPattern::Class(name) => Ok(Matcher::Set(
+                    SymbolClass::find(classes, &name)?
+                        .cells
+                        .iter()
+                        .copied()
+                        .collect(),
+                )),
+            })
+            .collect::<Result<_>>()?;
+        program.rules.entry(istate).or_default().push(Rule {
+            command,
+            icells,
+            ostate,
+            ocells: writers,
+            directions,
+            rank,
+        });
+    }
+    for rules in program.rules.values_mut() {
+        rules.sort_by(|a, b| b.rank.cmp(&a.rank));
+    }
+    Ok(program)
+}
+
+/// Expands the commands into commands that read and write exact cells, where wildcards match
+/// `cells`. Cells matched by several commands are kept only for the one [`Program`] applies.
+pub fn expand_commands(
+    commands: &[MultiCommand],
+    cells: &[Cell],
+    classes: &[SymbolClass],
+) -> Result<Vec<MultiCommand>> {
+    // Later exact commands override earlier ones, so they go first.
+    let mut order: Vec<_> = (0..commands.len())
+        .rev()
+        .filter(|&n| commands[n].exact_icells().is_some())
+        .collect();
+    let mut patterns: Vec<_> = (0..commands.len())
+        .filter(|&n| commands[n].exact_icells().is_none())
+        .collect();
+    patterns.sort_by_cached_key(|&n| Reverse(commands[n].rank()));
+    order.extend(patterns);
+
+    let mut seen = HashSet::new();
+    let mut expanded = Vec::new();
+    for n in order {
+        let cmd = &commands[n];
+        let writers = cmd.writers(classes)?;
+        let choices = cmd
+            .icells
+            .iter()
+            .map(|pattern| match pattern {
+                CellPattern::Cell(cell) => Ok(vec![*cell]),
+                CellPattern::Any => Ok(cells.to_owned()),
+                CellPattern::NonBlank => Ok(cells
+                    .iter()
+                    .copied()
+                    .filter(|&cell| cell != Cell::Blank)
+                    .collect()),
+                CellPattern::Class(name) => Ok(SymbolClass::find(classes, name)?.cells.to_owned()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if choices.iter().any(Vec::is_empty) {
+            continue;
+        }
+        // Odometer over the choices of all tapes.
+        let mut indices = vec![0; choices.len()];
+        loop {
+            let icells: Vec<_> = indices.iter().zip(&choices).map(|(&i, c)| c[i]).collect();
+            if seen.insert((&cmd.istate, icells.to_owned())) {
+                let ocells = writers
+                    .iter()
+                    .zip(&icells)
+                    .map(|(writer, &cell)| writer.write(cell))
+                    .collect();
+                let mcmd = MultiCommand::new(
+                    cmd.istate.to_owned(),
+                    icells,
+                    cmd.ostate.to_owned(),
+                    ocells,
+                    cmd.directions.to_owned(),
+                )?;
+                expanded.push((n, mcmd));
+            }
+            let Some(t) = (0..indices.len())
+                .rev()
+                .find(|&t| indices[t] + 1 < choices[t].len())
+            else {
+                break;
+            };
+            indices[t] += 1;
+            indices[t + 1..].fill(0);
+        }
+    }
+    expanded.sort_by_key(|&(n, _)| n);
+    Ok(expanded.into_iter().map(|(_, cmd)| cmd).collect())
+}
+
+pub fn rename_states(commands: &mut [MultiCommand], names: &HashMap<String, String>) {
+    for cmd in commands {
+        for state in [&mut cmd.istate, &mut cmd.ostate] {
+            if let Some(name) = names.get(state) {
+                *state = name.to_owned();
+            }
+        }
+    }
+}
+
+/// Returns the states in breadth-first order of transitions from `start`, followed by the
+/// unreachable states in order of appearance.
+pub fn bfs_states(commands: &[MultiCommand], start: &str) -> Vec<String> {
+    let mut states = vec![start.to_owned()];
+    let mut seen = HashSet::from([start]);
+    let mut n = 0;
+    while n < states.len() {
+        let state = states[n].to_owned();
+        for cmd in commands.iter().filter(|cmd| cmd.istate == state) {
+            if !cmd.ostate.is_empty() && seen.insert(cmd.ostate.as_str()) {
+                states.push(cmd.ostate.to_owned());
+            }
+        }
+        n += 1;
+    }
+    for cmd in commands {
+        for state in [&cmd.istate, &cmd.ostate] {
+            if !state.is_empty() && seen.insert(state.as_str()) {
+                states.push(state.to_owned());
+            }
+        }
+    }
+    states
+}
+
 #[macro_export]
 macro_rules! tm_cmd {
     ($istate:literal, $icell:expr, $ostate:literal, $ocell:expr, $dir:literal $(,)?) => {
@@ -208,12 +773,14 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     fn test_1() {
         let cmd = MultiCommand::new("q0".into(), vec![], "q0".into(), vec![], vec![]);
         assert!(matches!(cmd, Err(_)));
     }
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     fn test_2() {
         let cmd = MultiCommand::new(
             "q0".into(),
@@ -226,6 +793,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     fn test_3() {
         let cmd = MultiCommand::new(
             "q".into(),
@@ -251,6 +819,259 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_5() {
+        let cmd = MultiCommand::with_patterns(
+            "q".into(),
+            vec![CellPattern::Any, CellPattern::Class("v".into())],
+            "q".into(),
+            vec![CellWrite::Same, 'a'.into()],
+            vec![Direction::None, Direction::None],
+        )
+        .unwrap();
+        assert_eq!(cmd.icells()[0].name('_'), "{*}");
+        assert_eq!(cmd.icells()[1].name('_'), "{v}");
+        assert_eq!(cmd.ocells()[0].name('_'), "{=}");
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(CellPattern::parse("_", '_').unwrap(), Cell::Blank.into());
+        assert_eq!(CellPattern::parse("ab", '_').unwrap(), "ab".into());
+        assert_eq!(CellPattern::parse("{*}", '_').unwrap(), CellPattern::Any);
+        assert_eq!(
+            CellPattern::parse("{+}", '_').unwrap(),
+            CellPattern::NonBlank
+        );
+        assert_eq!(
+            CellPattern::parse("{v}", '_').unwrap(),
+            CellPattern::Class("v".into())
+        );
+        assert!(CellPattern::parse("{=}", '_').is_err());
+        assert!(CellPattern::parse("{}", '_').is_err());
+        assert!(CellPattern::parse("a{", '_').is_err());
+        assert_eq!(CellWrite::parse("{=}", '_').unwrap(), CellWrite::Same);
+        assert_eq!(CellWrite::parse("_", '_').unwrap(), Cell::Blank.into());
+        assert!(CellWrite::parse("{*}", '_').is_err());
+    }
+
+    #[test]
+    fn test_build_program_1() {
+        let classes = [SymbolClass {
+            name: "v".into(),
+            cells: vec!['a'.into(), 'e'.into()],
+        }];
+        let cmds = vec![
+            tm_mcmd!("q", ['x'], "exact", ['y'], ['N']),
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![CellPattern::Any],
+                "any".into(),
+                vec![CellWrite::Same],
+                vec![Direction::Right],
+            )
+            .unwrap(),
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![CellPattern::NonBlank],
+                "non-blank".into(),
+                vec![CellWrite::Same],
+                vec![Direction::Right],
+            )
+            .unwrap(),
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![CellPattern::Class("v".into())],
+                "class".into(),
+                vec![Cell::Blank.into()],
+                vec![Direction::Left],
+            )
+            .unwrap(),
+        ];
+        let program = build_program_with_classes(cmds, &classes).unwrap();
+        let get = |cell: Cell| {
+            let transition = program.get("q", &[cell]).unwrap();
+            (transition.ostate.to_owned(), transition.ocells[0])
+        };
+        assert_eq!(get('x'.into()), ("exact".into(), 'y'.into()));
+        assert_eq!(get('e'.into()), ("class".into(), Cell::Blank));
+        assert_eq!(get('b'.into()), ("non-blank".into(), 'b'.into()));
+        assert_eq!(get(Cell::Blank), ("any".into(), Cell::Blank));
+        assert!(program.get("p", &[Cell::Blank]).is_none());
+    }
+
+    #[test]
+    fn test_build_program_multitape() {
+        let classes = [SymbolClass {
+            name: "v".into(),
+            cells: vec!['a'.into(), 'b'.into()],
+        }];
+        let cmd = |icells: Vec<CellPattern>, ostate: &str| {
+            MultiCommand::with_patterns(
+                "q".into(),
+                icells,
+                ostate.into(),
+                vec![CellWrite::Same, CellWrite::Same],
+                vec![Direction::None, Direction::None],
+            )
+            .unwrap()
+        };
+        let class = || CellPattern::Class("v".into());
+        let cmds = vec![
+            cmd(vec![class(), class()], "class-class"),
+            cmd(vec![class(), CellPattern::NonBlank], "class-non-blank"),
+            cmd(vec!['a'.into(), CellPattern::Any], "exact-any"),
+        ];
+        let program = build_program_with_classes(cmds, &classes).unwrap();
+        let get = |icells: [char; 2]| program.get("q", &icells.map(Cell::from)).unwrap().ostate;
+        assert_eq!(get(['a', 'b']), "exact-any");
+        assert_eq!(get(['a', 'c']), "exact-any");
+        assert_eq!(get(['b', 'b']), "class-class");
+        assert_eq!(get(['b', 'c']), "class-non-blank");
+    }
+
+    #[test]
+    fn test_build_program_2() {
+        let cmd = MultiCommand::with_patterns(
+            "q".into(),
+            vec![CellPattern::Class("v".into())],
+            "q".into(),
+            vec![CellWrite::Same],
+            vec![Direction::Right],
+        )
+        .unwrap();
+        assert!(build_program(vec![cmd]).is_err());
+        let cmd = MultiCommand::with_patterns(
+            "q".into(),
+            vec!['a'.into()],
+            "q".into(),
+            vec![CellWrite::Same],
+            vec![Direction::Right],
+        )
+        .unwrap();
+        let program = build_program(vec![cmd]).unwrap();
+        assert_eq!(
+            program.get("q", &['a'.into()]).unwrap().ocells,
+            vec!['a'.into()]
+        );
+    }
+
+    #[test]
+    fn test_build_program_3() {
+        let classes = [
+            SymbolClass {
+                name: "l".into(),
+                cells: vec!['a'.into(), 'b'.into()],
+            },
+            SymbolClass {
+                name: "u".into(),
+                cells: vec!['A'.into(), 'B'.into()],
+            },
+            SymbolClass {
+                name: "x".into(),
+                cells: vec!['X'.into()],
+            },
+        ];
+        let cmd = |icell: CellPattern, ocell: &str| {
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![icell],
+                "q".into(),
+                vec![CellWrite::Class(ocell.into())],
+                vec![Direction::Right],
+            )
+            .unwrap()
+        };
+        let program =
+            build_program_with_classes(vec![cmd(CellPattern::Class("l".into()), "u")], &classes)
+                .unwrap();
+        assert_eq!(
+            program.get("q", &['b'.into()]).unwrap().ocells,
+            vec!['B'.into()]
+        );
+        assert!(program.get("q", &['B'.into()]).is_none());
+        let cmds = vec![cmd(CellPattern::Class("l".into()), "x")];
+        assert!(build_program_with_classes(cmds, &classes).is_err());
+        let cmds = vec![cmd(CellPattern::Any, "u")];
+        assert!(build_program_with_classes(cmds, &classes).is_err());
+    }
+
+    #[test]
+    fn test_expand_commands() {
+        let classes = [SymbolClass {
+            name: "v".into(),
+            cells: vec!['a'.into()],
+        }];
+        let cells = [Cell::Blank, 'a'.into(), 'b'.into()];
+        let cmds = vec![
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![CellPattern::Any, CellPattern::NonBlank],
+                "p".into(),
+                vec![CellWrite::Same, CellWrite::Same],
+                vec![Direction::Right, Direction::None],
+            )
+            .unwrap(),
+            MultiCommand::with_patterns(
+                "q".into(),
+                vec![CellPattern::Class("v".into()), CellPattern::Any],
+                "r".into(),
+                vec![Cell::Blank.into(), CellWrite::Same],
+                vec![Direction::Left, Direction::None],
+            )
+            .unwrap(),
+            tm_mcmd!("q", ['b', 'b'], "s", ['a', 'a'], ['N', 'N']),
+        ];
+        let expanded = expand_commands(&cmds, &cells, &classes).unwrap();
+        assert_eq!(
+            expanded,
+            vec![
+                tm_mcmd!("q", [Cell::Blank, 'a'], "p", [Cell::Blank, 'a'], ['R', 'N']),
+                tm_mcmd!("q", [Cell::Blank, 'b'], "p", [Cell::Blank, 'b'], ['R', 'N']),
+                tm_mcmd!("q", ['b', 'a'], "p", ['b', 'a'], ['R', 'N']),
+                tm_mcmd!(
+                    "q",
+                    ['a', Cell::Blank],
+                    "r",
+                    [Cell::Blank, Cell::Blank],
+                    ['L', 'N']
+                ),
+                tm_mcmd!("q", ['a', 'a'], "r", [Cell::Blank, 'a'], ['L', 'N']),
+                tm_mcmd!("q", ['a', 'b'], "r", [Cell::Blank, 'b'], ['L', 'N']),
+                tm_mcmd!("q", ['b', 'b'], "s", ['a', 'a'], ['N', 'N']),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_states() {
+        let mut cmds = tm_mcmds![
+            ["a", ['0'], "b", ['0'], ['R']],
+            ["b", ['0'], "a", ['0'], ['R']],
+        ];
+        let names = HashMap::from([("a".into(), "b".into()), ("b".into(), "c".into())]);
+        rename_states(&mut cmds, &names);
+        assert_eq!(
+            cmds,
+            tm_mcmds![
+                ["b", ['0'], "c", ['0'], ['R']],
+                ["c", ['0'], "b", ['0'], ['R']],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bfs_states() {
+        let cmds = tm_mcmds![
+            ["x", ['0'], "y", ['0'], ['R']],
+            ["s", ['0'], "b", ['0'], ['R']],
+            ["a", ['0'], "z", ['0'], ['R']],
+            ["s", ['1'], "a", ['0'], ['R']],
+            ["b", ['0'], "s", ['0'], ['R']],
+        ];
+        assert_eq!(bfs_states(&cmds, "s"), ["s", "b", "a", "z", "x", "y"]);
+    }
+
     #[test]
     fn test_tm_cmd() {
         let cmd1 = tm_cmd!("q0", '0', "q0", '1', 'R');
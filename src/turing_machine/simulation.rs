@@ -0,0 +1,72 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::exhaustive_words::GetExhaustiveWords;
+
+use super::cell::Cell;
+use super::core::MultiCommand;
+use super::single_tape::simulated_state;
+use super::tape::Tape;
+use super::TuringMachine;
+
+/// Returns the non-blank cells and the head of the tape.
+fn normalize(tape: &Tape) -> (Vec<(isize, Cell)>, isize) {
+    let cells = tape
+        .range()
+        .map(|x| (x, *tape.get_at(x)))
+        .filter(|x| x.1 != Cell::Blank)
+        .collect();
+    (cells, tape.head())
+}
+
+fn run(tm: &mut TuringMachine) {
+    for _ in 0..1_000_000 {
+        if !tm.step() {
+            return;
+        }
+    }
+    panic!("the machine does not halt");
+}
+
+/// Checks that the compiled program ends like the original one on all inputs up to `length`.
+///
+/// `encode` makes the start tapes of the compiled program from an input, and `decode` makes the
+/// original tapes from its final tapes.
+pub fn check(
+    commands: Vec<MultiCommand>,
+    compiled: Vec<MultiCommand>,
+    compiled_start: &str,
+    alphabet: &[char],
+    length: usize,
+    encode: impl Fn(&str) -> Vec<String>,
+    decode: impl Fn(&[Tape]) -> Vec<Tape>,
+) {
+    let num_tapes = commands[0].len();
+    let start = commands[0].istate.to_owned();
+    let mut tm = TuringMachine::from_multi(&vec![""; num_tapes], commands).unwrap();
+    let compiled_tapes = compiled[0].len();
+    let mut simulation = TuringMachine::from_multi(&vec![""; compiled_tapes], compiled).unwrap();
+    let words = (1..=length).flat_map(|n| alphabet.get_exhaustive_words(n));
+    for word in [String::new()].into_iter().chain(words) {
+        let mut tapes = vec![""; num_tapes];
+        tapes[0] = &word;
+        tm.restart(&tapes, &vec![0; num_tapes], start.to_owned())
+            .unwrap();
+        run(&mut tm);
+        let encoded = encode(&word);
+        let tapes: Vec<_> = encoded.iter().map(String::as_str).collect();
+        simulation
+            .restart(&tapes, &vec![0; compiled_tapes], compiled_start.into())
+            .unwrap();
+        run(&mut simulation);
+        assert_eq!(
+            simulated_state(simulation.current_state()),
+            tm.current_state(),
+            "{word}"
+        );
+        let decoded: Vec<_> = decode(simulation.tapes()).iter().map(normalize).collect();
+        let expected: Vec<_> = tm.tapes().iter().map(normalize).collect();
+        assert_eq!(decoded, expected, "{word}");
+    }
+}
@@ -0,0 +1,502 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use super::cell::Cell;
+use super::core::{build_program_with_classes, Direction, MultiCommand, Program, SymbolClass};
+use super::symbol::Symbol;
+#[cfg(test)]
+use super::tape::Tape;
+
+/// Separates the name of a simulated state from the phase of the simulation.
+pub const SEPARATOR: char = '~';
+const START: &str = "~start";
+const INPUT: &str = "~input";
+const REWIND: &str = "~rewind";
+
+/// Cell of a simulated tape, and whether its head is there.
+type Track = (Cell, bool);
+
+/// A step sweeps right from the leftmost head to read the cells under the heads, then back left
+/// to write them and move the head marks.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Phase {
+    Read(Vec<Option<Cell>>),
+    Write(Vec<bool>),
+    Mark(usize, Vec<bool>),
+    Back,
+    Seek,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct SimState {
+    state: String,
+    transition: Option<(Vec<Cell>, Vec<Direction>)>,
+    phase: Phase,
+}
+
+/// One-tape program that simulates a multitape one on the tracks of its tape, with the input on
+/// the first track and the other tracks empty.
+pub struct SingleTape {
+    pub commands: Vec<MultiCommand>,
+    pub start_state: String,
+    tracks: HashMap<Cell, Vec<Track>>,
+    #[cfg(test)]
+    blank: char,
+}
+
+impl SingleTape {
+    pub fn new(
+        commands: &[MultiCommand],
+        classes: &[SymbolClass],
+        input: &[Cell],
+        start: &str,
+        blank: char,
+    ) -> Result<Self> {
+        let num_tapes = commands.first().map_or(1, MultiCommand::len);
+        let mut names = commands.iter().flat_map(|x| [&x.istate, &x.ostate]);
+        if start.contains(SEPARATOR) || names.any(|x| x.contains(SEPARATOR)) {
+            return Err(anyhow!("state names must not contain {SEPARATOR:?}"));
+        }
+        let mut states: Vec<&str> = Vec::new();
+        for cmd in commands {
+            if !states.contains(&cmd.istate.as_str()) {
+                states.push(&cmd.istate);
+            }
+        }
+        let program = build_program_with_classes(commands.to_owned(), classes)?;
+        let mut compiler = Compiler {
+            program,
+            num_tapes,
+            blank,
+            tracks: HashMap::new(),
+            commands: Vec::new(),
+        };
+        let input: Vec<_> = input
+            .iter()
+            .copied()
+            .filter(|&x| x != Cell::Blank)
+            .collect();
+        let cells = compiler.reachable_cells(&states, &input);
+        compiler.init(&input, start)?;
+        compiler.simulate(&cells, start)?;
+        for cell in &input {
+            if compiler.tracks.contains_key(cell) {
+                return Err(anyhow!("track symbol {cell} is an input symbol"));
+            }
+        }
+        Ok(Self {
+            commands: compiler.commands,
+            start_state: START.into(),
+            tracks: compiler.tracks,
+            #[cfg(test)]
+            blank,
+        })
+    }
+
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols: Vec<_> = self
+            .tracks
+            .keys()
+            .filter_map(|cell| match cell {
+                Cell::Blank => None,
+                Cell::Symbol(symbol) => Some(*symbol),
+            })
+            .collect();
+        symbols.sort_by_key(|x| x.name());
+        symbols
+    }
+
+    #[cfg(test)]
+    pub fn decode(&self, tape: &Tape) -> Vec<Tape> {
+        let num_tapes = self.tracks.values().next().map_or(1, Vec::len);
+        let mut tapes = vec![Tape::with_blank("", self.blank); num_tapes];
+        for position in tape.range() {
+            let cell = tape.get_at(position);
+            let Some(tracks) = self.tracks.get(cell) else {
+                tapes[0].set(position, *cell);
+                continue;
+            };
+            for (tape, &(cell, is_head)) in tapes.iter_mut().zip(tracks) {
+                tape.set(position, cell);
+                if is_head {
+                    tape.set_head(position);
+                }
+            }
+        }
+        tapes
+    }
+}
+
+/// Returns the simulated state of a state of a compiled program.
+#[cfg(test)]
+pub fn simulated_state(state: &str) -> &str {
+    state.split(SEPARATOR).next().unwrap_or_default()
+}
+
+struct Compiler {
+    program: Program,
+    num_tapes: usize,
+    blank: char,
+    tracks: HashMap<Cell, Vec<Track>>,
+    commands: Vec<MultiCommand>,
+}
+
+impl Compiler {
+    /// Returns the cells that can appear on each tape, starting from the input.
+    fn reachable_cells(&self, states: &[&str], input: &[Cell]) -> Vec<Vec<Cell>> {
+        let mut cells = vec![vec![Cell::Blank]; self.num_tapes];
+        cells[0].extend(input);
+        loop {
+            let mut is_changed = false;
+            for &state in states {
+                for icells in product(&cells) {
+                    let Some(transition) = self.program.get(state, &icells) else {
+                        continue;
+                    };
+                    for (cells, cell) in cells.iter_mut().zip(transition.ocells) {
+                        if !cells.contains(&cell) {
+                            cells.push(cell);
+                            is_changed = true;
+                        }
+                    }
+                }
+            }
+            if !is_changed {
+                return cells;
+            }
+        }
+    }
+
+    fn encode(&mut self, tracks: &[Track]) -> Result<Cell> {
+        if tracks.iter().all(|&x| x == (Cell::Blank, false)) {
+            self.tracks.insert(Cell::Blank, tracks.to_owned());
+            return Ok(Cell::Blank);
+        }
+        let names: Vec<_> = tracks
+            .iter()
+            .map(|(cell, is_head)| cell.name(self.blank) + if *is_head { "^" } else { "" })
+            .collect();
+        let cell = Cell::Symbol(Symbol::parse(&format!("[{}]", names.join("|")))?);
+        match self.tracks.get(&cell) {
+            Some(x) if x != tracks => Err(anyhow!("ambiguous track symbol {cell}")),
+            Some(_) => Ok(cell),
+            None => {
+                self.tracks.insert(cell, tracks.to_owned());
+                Ok(cell)
+            }
+        }
+    }
+
+    fn push(&mut self, istate: &str, icell: Cell, ostate: &str, ocell: Cell, direction: Direction) {
+        self.commands.push(
+            MultiCommand::new(
+                istate.to_owned(),
+                vec![icell],
+                ostate.to_owned(),
+                vec![ocell],
+                vec![direction],
+            )
+            .expect("one tape"),
+        );
+    }
+
+    /// Replaces the input with track symbols and rewinds to the first cell.
+    fn init(&mut self, input: &[Cell], start: &str) -> Result<()> {
+        let mut written = Vec::new();
+        for &cell in [Cell::Blank].iter().chain(input) {
+            let mut tracks = vec![(Cell::Blank, true); self.num_tapes];
+            tracks[0].0 = cell;
+            let ocell = self.encode(&tracks)?;
+            self.push(START, cell, INPUT, ocell, Direction::Right);
+            written.push(ocell);
+        }
+        for &cell in input {
+            let mut tracks = vec![(Cell::Blank, false); self.num_tapes];
+            tracks[0].0 = cell;
+            let ocell = self.encode(&tracks)?;
+            self.push(INPUT, cell, INPUT, ocell, Direction::Right);
+            written.push(ocell);
+        }
+        self.push(INPUT, Cell::Blank, REWIND, Cell::Blank, Direction::Left);
+        for cell in written {
+            self.push(REWIND, cell, REWIND, cell, Direction::Left);
+        }
+        self.push(REWIND, Cell::Blank, start, Cell::Blank, Direction::Right);
+        Ok(())
+    }
+
+    fn simulate(&mut self, cells: &[Vec<Cell>], start: &str) -> Result<()> {
+        let marked: Vec<Vec<Track>> = cells
+            .iter()
+            .map(|x| {
+                x.iter()
+                    .flat_map(|&cell| [(cell, false), (cell, true)])
+                    .collect()
+            })
+            .collect();
+        let mut all_tracks = Vec::new();
+        for tracks in product(&marked) {
+            let cell = self.encode(&tracks)?;
+            all_tracks.push((tracks, cell));
+        }
+        let start = SimState {
+            state: start.to_owned(),
+            transition: None,
+            phase: Phase::Read(vec![None; self.num_tapes]),
+        };
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = vec![start];
+        let mut n = 0;
+        while n < queue.len() {
+            let from = queue[n].clone();
+            n += 1;
+            let from_name = self.name(&from);
+            for (tracks, icell) in &all_tracks {
+                let Some((ocell, direction, to)) = self.next(&from, tracks)? else {
+                    continue;
+                };
+                self.push(&from_name, *icell, &self.name(&to), ocell, direction);
+                if seen.insert(to.clone()) {
+                    queue.push(to);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn next(
+        &mut self,
+        from: &SimState,
+        tracks: &[Track],
+    ) -> Result<Option<(Cell, Direction, SimState)>> {
+        let cell = self.encode(tracks)?;
+        let to = |transition: Option<(Vec<Cell>, Vec<Direction>)>, phase| SimState {
+            state: from.state.to_owned(),
+            transition,
+            phase,
+        };
+        let is_head = tracks.iter().any(|x| x.1);
+        let res = match &from.phase {
+            Phase::Read(read) => {
+                let read: Vec<_> = read
+                    .iter()
+                    .zip(tracks)
+                    .map(|(&read, &(cell, is_head))| read.or(is_head.then_some(cell)))
+                    .collect();
+                if read.iter().all(Option::is_some) {
+                    let icells: Vec<_> = read.into_iter().flatten().collect();
+                    let Some(transition) = self.program.get(&from.state, &icells) else {
+                        return Ok(None);
+                    };
+                    let next = SimState {
+                        state: transition.ostate.to_owned(),
+                        transition: Some((transition.ocells, transition.directions.to_owned())),
+                        phase: Phase::Write(vec![false; self.num_tapes]),
+                    };
+                    (cell, Direction::None, next)
+                } else {
+                    (cell, Direction::Right, to(None, Phase::Read(read)))
+                }
+            }
+            Phase::Write(done) => {
+                let (ocells, directions) = from.transition.clone().expect("writing");
+                let Some(n) = (0..self.num_tapes).find(|&n| tracks[n].1 && !done[n]) else {
+                    return Ok(Some((
+                        cell,
+                        Direction::Left,
+                        to(Some((ocells, directions)), Phase::Write(done.to_owned())),
+                    )));
+                };
+                let mut tracks = tracks.to_owned();
+                let mut done = done.to_owned();
+                done[n] = true;
+                tracks[n].0 = ocells[n];
+                let ocell;
+                let next = if directions[n] == Direction::None {
+                    ocell = self.encode(&tracks)?;
+                    if done.iter().all(|&x| x) {
+                        return Ok(Some((ocell, Direction::Left, to(None, Phase::Seek))));
+                    }
+                    (Direction::None, Phase::Write(done))
+                } else {
+                    tracks[n].1 = false;
+                    ocell = self.encode(&tracks)?;
+                    (directions[n], Phase::Mark(n, done))
+                };
+                (ocell, next.0, to(Some((ocells, directions)), next.1))
+            }
+            Phase::Mark(n, done) => {
+                let (ocells, directions) = from.transition.clone().expect("marking");
+                let mut tracks = tracks.to_owned();
+                tracks[*n].1 = true;
+                let ocell = self.encode(&tracks)?;
+                let is_done = done.iter().all(|&x| x);
+                match (directions[*n], is_done) {
+                    (Direction::Left, true) => (ocell, Direction::None, to(None, Phase::Seek)),
+                    (Direction::Left, false) => (
+                        ocell,
+                        Direction::Right,
+                        to(Some((ocells, directions)), Phase::Write(done.to_owned())),
+                    ),
+                    (_, true) => (ocell, Direction::Left, to(None, Phase::Back)),
+                    (_, false) => (
+                        ocell,
+                        Direction::Left,
+                        to(Some((ocells, directions)), Phase::Write(done.to_owned())),
+                    ),
+                }
+            }
+            Phase::Back => (cell, Direction::Left, to(None, Phase::Seek)),
+            Phase::Seek if is_head => (
+                cell,
+                Direction::None,
+                to(None, Phase::Read(vec![None; self.num_tapes])),
+            ),
+            Phase::Seek => (cell, Direction::Right, to(None, Phase::Seek)),
+        };
+        Ok(Some(res))
+    }
+
+    fn name(&self, state: &SimState) -> String {
+        let names = |cells: &mut dyn Iterator<Item = Option<Cell>>| {
+            cells
+                .map(|x| x.map_or("?".into(), |x| x.name(self.blank)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let flags = |flags: &[bool]| -> String {
+            flags.iter().map(|&x| if x { '+' } else { '-' }).collect()
+        };
+        let transition = || {
+            let (ocells, directions) = state.transition.as_ref().expect("writing");
+            let directions: String = directions.iter().map(|x| x.to_string()).collect();
+            format!(
+                "{};{directions}",
+                names(&mut ocells.iter().copied().map(Some))
+            )
+        };
+        let phase = match &state.phase {
+            Phase::Read(read) if read.iter().all(Option::is_none) => {
+                return state.state.to_owned();
+            }
+            Phase::Read(read) => format!("r[{}]", names(&mut read.iter().copied())),
+            Phase::Write(done) => format!("w[{};{}]", transition(), flags(done)),
+            Phase::Mark(n, done) => format!("m{}[{};{}]", n + 1, transition(), flags(done)),
+            Phase::Back => "b".into(),
+            Phase::Seek => "s".into(),
+        };
+        format!("{}{SEPARATOR}{phase}", state.state)
+    }
+}
+
+pub fn product<T: Clone>(sets: &[Vec<T>]) -> Vec<Vec<T>> {
+    let mut tuples = vec![Vec::new()];
+    for set in sets {
+        tuples = tuples
+            .into_iter()
+            .flat_map(|tuple| {
+                set.iter().map(move |item| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(item.clone());
+                    tuple
+                })
+            })
+            .collect();
+    }
+    tuples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turing_machine::simulation;
+
+    fn cmd(istate: &str, icells: &str, ostate: &str, ocells: &str, dirs: &str) -> MultiCommand {
+        let cells = |s: &str| {
+            s.chars()
+                .map(|ch| if ch == '_' { Cell::Blank } else { ch.into() })
+                .collect()
+        };
+        let dirs = dirs
+            .chars()
+            .map(|x| Direction::from_char(x).unwrap())
+            .collect();
+        MultiCommand::new(
+            istate.into(),
+            cells(icells),
+            ostate.into(),
+            cells(ocells),
+            dirs,
+        )
+        .unwrap()
+    }
+
+    fn palindromes() -> Vec<MultiCommand> {
+        let mut commands = vec![
+            cmd("q0", "a_", "q0", "aa", "RR"),
+            cmd("q0", "b_", "q0", "bb", "RR"),
+            cmd("q0", "__", "q1", "__", "LL"),
+            cmd("q1", "__", "qz", "1_", "NN"),
+            cmd("q2", "__", "qz", "1_", "NN"),
+        ];
+        for x in ['a', 'b'] {
+            for y in ['a', 'b'] {
+                let xy = format!("{x}{y}");
+                commands.push(cmd("q1", &xy, "q1", &xy, "LN"));
+                if x == y {
+                    commands.push(cmd("q2", &xy, "q2", &xy, "RL"));
+                } else {
+                    commands.push(cmd("q2", &xy, "qz", &format!("0{y}"), "NN"));
+                }
+            }
+            commands.push(cmd("q1", &format!("_{x}"), "q2", &format!("_{x}"), "RN"));
+        }
+        commands
+    }
+
+    fn check(commands: Vec<MultiCommand>, alphabet: &[char], length: usize) {
+        let input: Vec<Cell> = alphabet.iter().map(|&x| x.into()).collect();
+        let compiled = SingleTape::new(&commands, &[], &input, &commands[0].istate, '_').unwrap();
+        assert!(compiled.commands.iter().all(|x| x.len() == 1));
+        simulation::check(
+            commands,
+            compiled.commands.to_owned(),
+            &compiled.start_state,
+            alphabet,
+            length,
+            |word| vec![word.into()],
+            |tapes| compiled.decode(&tapes[0]),
+        );
+    }
+
+    #[test]
+    fn test_1() {
+        check(palindromes(), &['a', 'b'], 6);
+    }
+
+    #[test]
+    fn test_preset() {
+        check(crate::Application::preset_multitape(), &['a', 'b', 'c'], 4);
+    }
+
+    #[test]
+    fn test_2() {
+        let compiled =
+            SingleTape::new(&palindromes(), &[], &['a'.into(), 'b'.into()], "q0", '_').unwrap();
+        assert_eq!(compiled.start_state, "~start");
+        assert!(compiled.symbols().contains(&Symbol::new("[a^|_^]")));
+        assert_eq!(simulated_state("q1~w[a,b;RL;+-]"), "q1");
+        assert_eq!(simulated_state("q1"), "q1");
+    }
+
+    #[test]
+    fn test_3() {
+        let commands = vec![cmd("q~0", "a", "q1", "b", "R")];
+        assert!(SingleTape::new(&commands, &[], &['a'.into()], "q~0", '_').is_err());
+    }
+}
@@ -0,0 +1,152 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::cell::Cell;
+use super::core::Direction;
+use super::tape::Tape;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HaltReason {
+    /// The state has no commands at all.
+    FinalState,
+    NoCommand(Vec<Cell>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunStats {
+    pub steps: usize,
+    /// Largest number of cells each tape has spanned.
+    pub max_extents: Vec<usize>,
+    pub head_moves: Vec<usize>,
+    visited: Vec<HashSet<isize>>,
+    /// Number of configurations in each state, including the start one.
+    pub state_visits: HashMap<String, usize>,
+    pub command_fires: Vec<usize>,
+    pub crossings: Option<Crossings>,
+}
+
+impl RunStats {
+    pub fn new(tapes: &[Tape], state: &str, records_crossings: bool) -> Self {
+        Self {
+            steps: 0,
+            max_extents: tapes.iter().map(extent).collect(),
+            head_moves: vec![0; tapes.len()],
+            visited: tapes.iter().map(|x| HashSet::from([x.head()])).collect(),
+            state_visits: HashMap::from([(state.to_owned(), 1)]),
+            command_fires: Vec::new(),
+            crossings: records_crossings.then(Crossings::default),
+        }
+    }
+
+    pub fn cells_visited(&self) -> Vec<usize> {
+        self.visited.iter().map(HashSet::len).collect()
+    }
+
+    pub fn record_step(
+        &mut self,
+        tapes: &[Tape],
+        state: &str,
+        directions: &[Direction],
+        command: Option<usize>,
+    ) {
+        self.steps += 1;
+        for (n, (tape, direction)) in tapes.iter().zip(directions).enumerate() {
+            self.max_extents[n] = self.max_extents[n].max(extent(tape));
+            if *direction != Direction::None {
+                self.head_moves[n] += 1;
+                self.visited[n].insert(tape.head());
+            }
+        }
+        if let (Some(crossings), Some(tape)) = (&mut self.crossings, tapes.first()) {
+            match directions[0] {
+                Direction::Left => crossings.record(tape.head() + 1, state),
+                Direction::Right => crossings.record(tape.head(), state),
+                Direction::None => {}
+            }
+        }
+        match self.state_visits.get_mut(state) {
+            Some(visits) => *visits += 1,
+            None => {
+                self.state_visits.insert(state.to_owned(), 1);
+            }
+        }
+        if let Some(command) = command {
+            if self.command_fires.len() <= command {
+                self.command_fires.resize(command + 1, 0);
+            }
+            self.command_fires[command] += 1;
+        }
+    }
+}
+
+/// States in which the head of the first tape crosses each boundary, where the boundary `b`
+/// lies between the cells `b - 1` and `b`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Crossings {
+    states: Vec<String>,
+    sequences: BTreeMap<isize, Vec<usize>>,
+}
+
+impl Crossings {
+    pub fn record(&mut self, boundary: isize, state: &str) {
+        let index = match self.states.iter().position(|x| x == state) {
+            Some(n) => n,
+            None => {
+                self.states.push(state.to_owned());
+                self.states.len() - 1
+            }
+        };
+        self.sequences.entry(boundary).or_default().push(index);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (isize, Vec<&str>)> {
+        self.sequences.iter().map(|(&boundary, sequence)| {
+            let states = sequence.iter().map(|&n| self.states[n].as_str()).collect();
+            (boundary, states)
+        })
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.sequences.values().map(Vec::len).max().unwrap_or(0)
+    }
+}
+
+fn extent(tape: &Tape) -> usize {
+    let range = tape.range();
+    (range.end() - range.start() + 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossings() {
+        let mut tape = Tape::with_blank("ab", '_');
+        let mut stats = RunStats::new(&[tape.clone()], "q0", true);
+        tape.right();
+        stats.record_step(&[tape.clone()], "q1", &[Direction::Right], None);
+        tape.left();
+        stats.record_step(&[tape.clone()], "q2", &[Direction::Left], None);
+        tape.left();
+        stats.record_step(&[tape.clone()], "q3", &[Direction::Left], None);
+        stats.record_step(&[tape.clone()], "q4", &[Direction::None], None);
+        // Both moves between the cells 0 and 1 cross the boundary 1.
+        let crossings: Vec<_> = stats.crossings.as_ref().unwrap().iter().collect();
+        assert_eq!(crossings, [(0, vec!["q3"]), (1, vec!["q1", "q2"])]);
+        assert_eq!(stats.crossings.unwrap().max_len(), 2);
+    }
+
+    #[test]
+    fn test_crossings_off() {
+        let mut tape = Tape::with_blank("ab", '_');
+        let mut stats = RunStats::new(&[tape.clone()], "q0", false);
+        tape.right();
+        stats.record_step(&[tape], "q1", &[Direction::Right], None);
+        assert_eq!(stats.crossings, None);
+        assert_eq!(stats.head_moves, [1]);
+    }
+}
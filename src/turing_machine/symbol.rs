@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: 2024 k4leg <pOgtq@yandex.com>
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Default)]
+struct Interner {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+/// Tape symbol with an interned, possibly multi-character name.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const OPEN: char = '{';
+    pub const CLOSE: char = '}';
+
+    pub fn new(name: &str) -> Self {
+        if let Some(&id) = interner().read().unwrap().ids.get(name) {
+            return Self(id);
+        }
+        let mut interner = interner().write().unwrap();
+        if let Some(&id) = interner.ids.get(name) {
+            return Self(id);
+        }
+        // Symbols live as long as the application, so leaking the names is fine.
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let id = interner.names.len() as u32;
+        interner.names.push(name);
+        interner.ids.insert(name, id);
+        Self(id)
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|ch| ch.is_whitespace() || ch == Self::OPEN || ch == Self::CLOSE)
+        {
+            return Err(anyhow!("invalid symbol: {name:?}"));
+        }
+        Ok(Self::new(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        interner().read().unwrap().names[self.0 as usize]
+    }
+
+    pub fn is_char(self) -> bool {
+        let mut chars = self.name().chars();
+        chars.next().is_some() && chars.next().is_none()
+    }
+}
+
+impl From<char> for Symbol {
+    fn from(value: char) -> Self {
+        Self::new(value.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.name().fmt(f)
+    }
+}
+
+/// Wraps names longer than one character in braces.
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_char() {
+            self.name().fmt(f)
+        } else {
+            write!(f, "{}{}{}", Self::OPEN, self.name(), Self::CLOSE)
+        }
+    }
+}
+
+/// Splits a sequence of symbols written as by [`Symbol`]'s `Display` into names.
+///
+/// An unclosed brace takes the rest of `s` as a name.
+pub fn split_names(s: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = s;
+    while let Some(ch) = rest.chars().next() {
+        if ch == Symbol::OPEN {
+            let inner = &rest[ch.len_utf8()..];
+            match inner.find(Symbol::CLOSE) {
+                Some(end) => {
+                    names.push(&inner[..end]);
+                    rest = &inner[end + Symbol::CLOSE.len_utf8()..];
+                }
+                None => {
+                    names.push(inner);
+                    rest = "";
+                }
+            }
+        } else {
+            names.push(&rest[..ch.len_utf8()]);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_1() {
+        assert_eq!(Symbol::new("X1"), Symbol::new("X1"));
+        assert_ne!(Symbol::new("X1"), Symbol::new("X2"));
+        assert_eq!(Symbol::new("a"), Symbol::from('a'));
+    }
+
+    #[test]
+    fn test_2() {
+        assert_eq!(Symbol::new("a'").name(), "a'");
+        assert_eq!(Symbol::new("a'").to_string(), "{a'}");
+        assert_eq!(Symbol::new("a").to_string(), "a");
+        assert_eq!(Symbol::new("λ").to_string(), "λ");
+    }
+
+    #[test]
+    fn test_3() {
+        assert!(Symbol::parse("#0").is_ok());
+        assert!(Symbol::parse("").is_err());
+        assert!(Symbol::parse("a b").is_err());
+        assert!(Symbol::parse("{").is_err());
+        assert!(Symbol::parse("a}").is_err());
+    }
+
+    #[test]
+    fn test_split_names() {
+        assert_eq!(split_names("ab{X1}c"), ["a", "b", "X1", "c"]);
+        assert_eq!(split_names("{a'}{#0}"), ["a'", "#0"]);
+        assert_eq!(split_names("λa{X"), ["λ", "a", "X"]);
+        assert_eq!(split_names(""), [] as [&str; 0]);
+    }
+}
@@ -5,8 +5,10 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::iter::Iterator;
+use std::ops::RangeInclusive;
 
-use super::cell::Cell;
+use super::cell::{Cell, BLANK_CHAR};
+use super::symbol::split_names;
 
 pub struct TapeIter<'a> {
     tape: &'a HashMap<isize, Cell>,
@@ -40,21 +42,44 @@ impl<'a> Iterator for TapeIter<'a> {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct Tape {
     tape: HashMap<isize, Cell>,
     min: isize,
     max: isize,
     head: isize,
+    blank: char,
 }
 
 impl Tape {
+    #[cfg(test)]
     pub fn new() -> Self {
         Self {
             tape: HashMap::new(),
             min: 0,
             max: 0,
             head: 0,
+            blank: BLANK_CHAR,
+        }
+    }
+
+    /// Creates a tape from `value`, where `blank` denotes blank cells both in `value` and in
+    /// the rendering of the tape. Multi-character symbols are written in braces, e.g. `a{X1}b`.
+    pub fn with_blank(value: &str, blank: char) -> Self {
+        let mut tape = HashMap::new();
+        let mut length = 0;
+        for (n, name) in split_names(value).into_iter().enumerate() {
+            if let cell @ Cell::Symbol(_) = Cell::from_name(name, blank) {
+                tape.insert(n as isize, cell);
+            }
+            length += 1;
+        }
+        Self {
+            tape,
+            min: 0,
+            max: length - 1,
+            head: 0,
+            blank,
         }
     }
 
@@ -68,6 +93,35 @@ impl Tape {
         self.trim();
     }
 
+    pub fn head(&self) -> isize {
+        self.head
+    }
+
+    pub fn set_head(&mut self, head: isize) {
+        self.head = head;
+        self.trim();
+    }
+
+    /// Returns the positions of the cells covered by [`Tape::iter`].
+    pub fn range(&self) -> RangeInclusive<isize> {
+        self.min.min(self.head)..=self.max.max(self.head)
+    }
+
+    pub fn get_at(&self, position: isize) -> &Cell {
+        match self.tape.get(&position) {
+            Some(cell) => cell,
+            None => &Cell::Blank,
+        }
+    }
+
+    pub fn set(&mut self, position: isize, cell: Cell) {
+        let head = self.head;
+        self.head = position;
+        self.write(cell);
+        self.head = head;
+        self.trim();
+    }
+
     pub fn get(&self) -> &Cell {
         match self.tape.get(&self.head) {
             Some(cell) => cell,
@@ -116,31 +170,62 @@ impl Tape {
         }
     }
 
-    pub fn iter(&self) -> TapeIter {
+    pub fn iter(&self) -> TapeIter<'_> {
         TapeIter::new(&self.tape, self.min, self.max, self.head)
     }
 
-    pub fn len(&self) -> usize {
-        (self.max - self.min + 1) as usize
+    pub fn to_string_with_state(&self, state: &str) -> String {
+        self.snapshot().to_string_with_state(state)
     }
 
+    pub fn snapshot(&self) -> TapeSnapshot {
+        TapeSnapshot {
+            cells: self.iter().collect(),
+            head: (self.head - self.range().start()) as usize,
+            position: self.head,
+            blank: self.blank,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapeSnapshot {
+    pub cells: Vec<Cell>,
+    /// Index of the head in `cells`.
+    pub head: usize,
+    /// Position of the head on the tape.
+    pub position: isize,
+    pub blank: char,
+}
+
+impl TapeSnapshot {
     pub fn to_string_with_state(&self, state: &str) -> String {
-        let mut s = self.to_string();
-        let idx = if self.head >= self.min {
-            (self.head - self.min).unsigned_abs()
-        } else {
-            0
-        };
-        s.insert_str(s.char_indices().collect::<Vec<_>>()[idx].0, state);
+        let mut s = String::new();
+        for (n, cell) in self.cells.iter().enumerate() {
+            if n == self.head {
+                s.push_str(state);
+            }
+            s.push_str(&cell.to_string_with_blank(self.blank));
+        }
         s
     }
 }
 
+impl fmt::Display for TapeSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        for cell in &self.cells {
+            s.push_str(&cell.to_string_with_blank(self.blank));
+        }
+        s.fmt(f)
+    }
+}
+
 impl fmt::Display for Tape {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut s = String::new();
         for i in self.iter() {
-            s.push(i.into());
+            s.push_str(&i.to_string_with_blank(self.blank));
         }
         s.fmt(f)
     }
@@ -148,23 +233,12 @@ impl fmt::Display for Tape {
 
 impl From<&str> for Tape {
     fn from(value: &str) -> Self {
-        let mut tape = HashMap::new();
-        for (n, ch) in value.chars().enumerate() {
-            tape.insert(n as isize, ch.into());
-        }
-        Self {
-            tape,
-            min: 0,
-            max: value.len() as isize - 1,
-            head: 0,
-        }
+        Self::with_blank(value, BLANK_CHAR)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::turing_machine::cell::BLANK_CHAR;
-
     use super::*;
 
     #[test]
@@ -186,7 +260,7 @@ mod tests {
     fn test_to_string_3() {
         let mut t = Tape::new();
         t.left();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         assert_eq!(t.to_string(), "a");
     }
 
@@ -203,18 +277,18 @@ mod tests {
         let mut t = Tape::new();
         t.left();
         t.left();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         assert_eq!(t.to_string(), "a");
     }
 
     #[test]
     fn test_to_string_6() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('b'));
+        t.write(Cell::from('b'));
         t.left();
         t.left();
         t.left();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         assert_eq!(t.to_string(), format!("a{BLANK_CHAR}{BLANK_CHAR}b"));
     }
 
@@ -249,23 +323,23 @@ mod tests {
     #[test]
     fn test_to_string_with_state_5() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         assert_eq!(t.to_string_with_state("q0"), "q0a");
     }
 
     #[test]
     fn test_to_string_with_state_6() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
-        t.write(Cell::Symbol('b'));
+        t.write(Cell::from('b'));
         assert_eq!(t.to_string_with_state("q0"), "aq0b");
     }
 
     #[test]
     fn test_to_string_with_state_7() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         t.write(Cell::Blank);
         assert_eq!(t.to_string_with_state("q0"), format!("aq0{BLANK_CHAR}"));
@@ -274,7 +348,7 @@ mod tests {
     #[test]
     fn test_to_string_with_state_8() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         t.right();
         t.right();
@@ -287,11 +361,11 @@ mod tests {
     #[test]
     fn test_to_string_with_state_9() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         t.right();
         t.right();
-        t.write(Cell::Symbol('b'));
+        t.write(Cell::from('b'));
         assert_eq!(
             t.to_string_with_state("q0"),
             format!("a{BLANK_CHAR}{BLANK_CHAR}q0b")
@@ -307,23 +381,23 @@ mod tests {
     #[test]
     fn test_get_2() {
         let mut t = Tape::new();
-        t.write(Cell::Symbol('a'));
-        assert_eq!(t.get(), &Cell::Symbol('a'));
+        t.write(Cell::from('a'));
+        assert_eq!(t.get(), &Cell::from('a'));
     }
 
     #[test]
     fn test_get_3() {
         let mut t = Tape::new();
         t.right();
-        t.write(Cell::Symbol('a'));
-        assert_eq!(t.get(), &Cell::Symbol('a'));
+        t.write(Cell::from('a'));
+        assert_eq!(t.get(), &Cell::from('a'));
     }
 
     #[test]
     fn test_get_4() {
         let mut t = Tape::new();
         t.right();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         assert_eq!(t.get(), &Cell::Blank);
     }
@@ -332,23 +406,52 @@ mod tests {
     fn test_get_5() {
         let mut t = Tape::new();
         t.right();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         t.left();
-        assert_eq!(t.get(), &Cell::Symbol('a'));
+        assert_eq!(t.get(), &Cell::from('a'));
     }
 
     #[test]
     fn test_get_6() {
         let mut t = Tape::new();
         t.right();
-        t.write(Cell::Symbol('a'));
+        t.write(Cell::from('a'));
         t.right();
         t.left();
         t.left();
         assert_eq!(t.get(), &Cell::Blank);
     }
 
+    #[test]
+    fn test_set_head_1() {
+        let mut t = Tape::from("abc");
+        t.set_head(2);
+        assert_eq!(t.get(), &Cell::from('c'));
+        assert_eq!(t.to_string_with_state("q0"), "abq0c");
+    }
+
+    #[test]
+    fn test_set_head_2() {
+        let mut t = Tape::from("ab");
+        t.set_head(-2);
+        assert_eq!(t.get(), &Cell::Blank);
+        assert_eq!(
+            t.to_string_with_state("q0"),
+            format!("q0{BLANK_CHAR}{BLANK_CHAR}ab")
+        );
+    }
+
+    #[test]
+    fn test_set_head_3() {
+        let mut t = Tape::from("ab");
+        t.set_head(4);
+        assert_eq!(
+            t.to_string_with_state("q0"),
+            format!("ab{BLANK_CHAR}{BLANK_CHAR}q0{BLANK_CHAR}")
+        );
+    }
+
     #[test]
     fn test_from_1() {
         let t = Tape::from("");
@@ -365,7 +468,82 @@ mod tests {
     fn test_from_3() {
         let mut t = Tape::from("abcd");
         t.right();
-        t.write(Cell::Symbol('X'));
+        t.write(Cell::from('X'));
         assert_eq!(t.to_string_with_state("q0"), "aq0Xcd");
     }
+
+    #[test]
+    fn test_from_4() {
+        let mut t = Tape::from(format!("a{BLANK_CHAR}b").as_str());
+        assert_eq!(t.to_string(), format!("a{BLANK_CHAR}b"));
+        t.right();
+        assert_eq!(t.get(), &Cell::Blank);
+        t.right();
+        assert_eq!(t.to_string_with_state("q0"), format!("a{BLANK_CHAR}q0b"));
+    }
+
+    #[test]
+    fn test_set_1() {
+        let mut t = Tape::from("abc");
+        t.set(1, Cell::from('X'));
+        assert_eq!(t.to_string_with_state("q0"), "q0aXc");
+        assert_eq!(t.get_at(1), &Cell::from('X'));
+    }
+
+    #[test]
+    fn test_set_2() {
+        let mut t = Tape::from("abc");
+        t.set(-2, Cell::from('X'));
+        assert_eq!(t.range(), -2..=2);
+        assert_eq!(t.to_string_with_state("q0"), format!("X{BLANK_CHAR}q0abc"));
+        t.set(-2, Cell::Blank);
+        assert_eq!(t.range(), 0..=2);
+        assert_eq!(t.to_string_with_state("q0"), "q0abc");
+    }
+
+    #[test]
+    fn test_set_3() {
+        let mut t = Tape::from("abc");
+        t.set(2, Cell::Blank);
+        t.set(0, Cell::Blank);
+        assert_eq!(t.get(), &Cell::Blank);
+        assert_eq!(t.to_string_with_state("q0"), format!("q0{BLANK_CHAR}b"));
+    }
+
+    #[test]
+    fn test_with_blank_1() {
+        let mut t = Tape::with_blank(&format!("a_{BLANK_CHAR}"), '_');
+        assert_eq!(t.to_string_with_state("q0"), format!("q0a_{BLANK_CHAR}"));
+        t.right();
+        assert_eq!(t.get(), &Cell::Blank);
+        t.right();
+        assert_eq!(t.get(), &Cell::from(BLANK_CHAR));
+        t.right();
+        assert_eq!(t.to_string_with_state("q0"), format!("a_{BLANK_CHAR}q0_"));
+    }
+
+    #[test]
+    fn test_with_blank_2() {
+        let mut t = Tape::with_blank("a{X1}_{a'}", '_');
+        t.right();
+        assert_eq!(t.get(), &Cell::from("X1"));
+        t.write(Cell::from("#0"));
+        t.right();
+        assert_eq!(t.get(), &Cell::Blank);
+        assert_eq!(t.to_string_with_state("q0"), "a{#0}q0_{a'}");
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut t = Tape::with_blank("ab", '_');
+        t.left();
+        let snapshot = t.snapshot();
+        assert_eq!(
+            snapshot.cells,
+            [Cell::Blank, Cell::from('a'), Cell::from('b')]
+        );
+        assert_eq!((snapshot.head, snapshot.position), (0, -1));
+        assert_eq!(snapshot.to_string_with_state("q0"), "q0_ab");
+        assert_eq!(snapshot.to_string(), "_ab");
+    }
 }